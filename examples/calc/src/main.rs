@@ -1,9 +1,9 @@
-use std::{cell::RefCell, rc::Rc, process::exit};
+use std::sync::{Arc, Mutex};
 
 use hp::{Parser, Template};
 
 fn main() {
-    let result = Rc::new(RefCell::new(0.));
+    let result = Arc::new(Mutex::new(0.));
 
     let mut parser = Parser::new()
         .with_author("Example")
@@ -16,12 +16,11 @@ fn main() {
             .matches("add")
             .matches("+")
             .with_help("Add two or more numbers supplied.")
-            .number_of_values(99)
-            .optional_values(false)
+            .at_least(2)
             .on_parse(move |values| {
                 for value in values {
                     if let Ok(v) = value.parse::<f64>() {
-                        *res.borrow_mut() += v;
+                        *res.lock().unwrap() += v;
                     }
                 }
             }),
@@ -32,12 +31,11 @@ fn main() {
             .matches("sub")
             .matches("-")
             .with_help("Substitute two or more numbers supplied.")
-            .number_of_values(99)
-            .optional_values(false)
+            .variadic(true)
             .on_parse(move |values| {
                 for value in values {
                     if let Ok(v) = value.parse::<f64>() {
-                        *res.borrow_mut() -= v
+                        *res.lock().unwrap() -= v
                     }
                 }
             }),
@@ -48,12 +46,11 @@ fn main() {
             .matches("mul")
             .matches("*")
             .with_help("Multiply two or more numbers supplied.")
-            .number_of_values(99)
-            .optional_values(false)
+            .variadic(true)
             .on_parse(move |values| {
                 for value in values {
                     if let Ok(v) = value.parse::<f64>() {
-                        *res.borrow_mut() *= v
+                        *res.lock().unwrap() *= v
                     }
                 }
             }),
@@ -64,21 +61,17 @@ fn main() {
             .matches("div")
             .matches("/")
             .with_help("Divide two or more numbers supplied.")
-            .number_of_values(99)
-            .optional_values(false)
+            .variadic(true)
             .on_parse(move |values| {
                 for value in values {
                     if let Ok(v) = value.parse::<f64>() {
-                        *res.borrow_mut() /= v
+                        *res.lock().unwrap() /= v
                     };
                 }
             }),
     );
 
-    if let Err(e) = parser.parse(None) {
-        println!("{e}");
-        exit(1);
-    }
+    parser.parse_or_exit();
 
-    println!("{}", result.borrow())
+    println!("{}", result.lock().unwrap())
 }
@@ -0,0 +1,148 @@
+//! Module containing the builder of git-style commands.
+use crate::{Parser, Template, TemplateId};
+
+/// A builder adding the options of a command, created by `Parser::command()`.
+///
+/// The options are subcommands of the command, so they're only matched after it.
+pub struct CommandBuilder<'a> {
+    parser: &'a mut Parser,
+    id: TemplateId,
+}
+
+impl CommandBuilder<'_> {
+    /// Return the `Template` ID of the command.
+    pub fn id(&self) -> TemplateId {
+        self.id
+    }
+
+    /// Add an option to the command.
+    ///
+    /// This method creates the `Template` for you, but it takes away some of the options.
+    pub fn add<M: AsRef<str>, H: AsRef<str>>(
+        self,
+        matches: M,
+        num_values: usize,
+        help_message: H,
+    ) -> Self {
+        let template = Template::new()
+            .matches(matches.as_ref())
+            .number_of_values(num_values)
+            .with_help(help_message.as_ref());
+        self.add_template(template)
+    }
+
+    /// Add an option to the command, given its `Template`.
+    pub fn add_template(self, mut template: Template) -> Self {
+        template.subarg(self.id);
+        self.parser.add_to_map(template);
+        self
+    }
+}
+
+impl Parser {
+    /// Add a command, which is matched only as the first positional argument, such as `commit`
+    /// in `git --no-pager commit -m "message"`.
+    ///
+    /// Once the command is matched, its options are looked up before the top-level arguments,
+    /// which stay available. A command appearing later is just a positional argument. The
+    /// matched command is returned by `ParsedArguments::subcommand()`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser
+    ///     .command("commit", "Record changes to the repository.")
+    ///     .add("-m", 1, "Use the given message.")
+    ///     .add("-a", 0, "Commit all changed files.");
+    /// parser.command("push", "Update the remote refs.");
+    /// let result = parser.parse(None)?;
+    ///
+    /// // $ myprog commit -a -m "message"
+    /// assert_eq!(result.subcommand(), Some("commit"));
+    /// ```
+    pub fn command<N: AsRef<str>, H: AsRef<str>>(
+        &mut self,
+        name: N,
+        help_message: H,
+    ) -> CommandBuilder<'_> {
+        let template = Template::new()
+            .matches(name.as_ref())
+            .with_help(help_message.as_ref());
+        let id = self.add_to_map(template);
+        self.commands.push(id);
+
+        CommandBuilder { parser: self, id }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    #[test]
+    fn commands() {
+        let mut parser = Parser::new();
+        parser.add("--verbose", 0, "Print more information.");
+        let commit = parser
+            .command("commit", "Record changes.")
+            .add("-m", 1, "Use the given message.")
+            .add("-a", 0, "Commit all changed files.")
+            .id();
+        parser
+            .command("push", "Update the remote.")
+            .add("-f", 0, "Force.");
+
+        let result = parser
+            .parse(Some(vec![
+                "--verbose",
+                "commit",
+                "-m",
+                "x",
+                "--verbose",
+                "-a",
+                "push",
+            ]))
+            .unwrap();
+        assert_eq!(result.subcommand(), Some("commit"));
+        let message = result.get_with_context(commit, "-m").unwrap();
+        assert_eq!(message.values(), &vec!["x"]);
+        assert!(result.has_with_context(commit, "-a"));
+        assert_eq!(result.count("--verbose"), 2);
+        assert_eq!(result.positionals(), &vec!["push"]);
+
+        let result = parser.parse(Some(vec!["file", "commit"])).unwrap();
+        assert_eq!(result.subcommand(), None);
+        assert_eq!(result.positionals(), &vec!["file", "commit"]);
+        assert!(parser.parse(Some(vec!["push", "-m", "x"])).is_err());
+    }
+
+    #[test]
+    fn nested_options() {
+        let mut parser = Parser::new();
+        parser.add("--verbose", 0, "Print more information.");
+        let remote = parser.command("remote", "Manage the remotes.").id();
+        let add = parser
+            .add_subcommand(remote, "add", 1, "Add a remote.")
+            .unwrap();
+        parser.add_subcommand(add, "-f", 0, "Fetch.").unwrap();
+        parser
+            .add_subcommand(add, "-t", 1, "Track a branch.")
+            .unwrap();
+
+        let result = parser
+            .parse(Some(vec![
+                "remote",
+                "add",
+                "x",
+                "-f",
+                "-t",
+                "main",
+                "--verbose",
+                "-f",
+            ]))
+            .unwrap();
+        assert_eq!(result.subcommand(), Some("remote"));
+        assert!(result.has_path(&["remote", "add", "-f"]));
+        assert!(result.has_path(&["remote", "add", "-t"]));
+        assert_eq!(result.count("--verbose"), 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! Module containing the shell completion script generation.
+use std::fmt::Write;
+
+use crate::{Parser, Template};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Shells for which a completion script can be generated.
+pub enum Shell {
+    /// The Bourne Again Shell, the script is meant to be sourced or placed into
+    /// `/etc/bash_completion.d/`.
+    Bash,
+    /// The Z Shell, the script is meant to be placed into a directory in `$fpath`.
+    Zsh,
+}
+
+/// Quote a string for use inside of a shell script.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+impl Parser {
+    /// Generate a completion script for the given shell.
+    ///
+    /// The script completes the top-level arguments and, once a command is present, its
+    /// subcommands. All the aliases of a template are offered.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().with_program_name("myprog");
+    /// let gen = parser.add("--generate-completion", 1, "Print a completion script.");
+    /// let result = parser.parse(None)?;
+    ///
+    /// if let Some(shell) = result.get_with_id(gen) {
+    ///     match shell.values()[0].as_str() {
+    ///         "bash" => println!("{}", parser.generate_completion(Shell::Bash)),
+    ///         "zsh" => println!("{}", parser.generate_completion(Shell::Zsh)),
+    ///         _ => (),
+    ///     }
+    /// }
+    ///
+    /// // $ myprog --generate-completion bash > /etc/bash_completion.d/myprog
+    /// ```
+    pub fn generate_completion(&self, shell: Shell) -> String {
+        let templates = self.unique_templates();
+        let name = &self.program_name;
+        let function: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        let mut result = String::new();
+        match shell {
+            Shell::Bash => {
+                writeln!(result, "_{function}() {{").unwrap_or(());
+                writeln!(result, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").unwrap_or(());
+                writeln!(result, "    local context=0 word").unwrap_or(());
+                writeln!(
+                    result,
+                    "    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do"
+                )
+                .unwrap_or(());
+                write_transitions(&mut result, &templates);
+                writeln!(result, "    done").unwrap_or(());
+                writeln!(result, "    case \"${{context}}\" in").unwrap_or(());
+                write_candidates(&mut result, self, |words| {
+                    format!("COMPREPLY=($(compgen -W \"{words}\" -- \"${{cur}}\"))")
+                });
+                writeln!(result, "    esac").unwrap_or(());
+                writeln!(result, "}}").unwrap_or(());
+                write!(result, "complete -F _{function} {}", quote(name)).unwrap_or(());
+            }
+            Shell::Zsh => {
+                writeln!(result, "#compdef {name}").unwrap_or(());
+                writeln!(result).unwrap_or(());
+                writeln!(result, "_{function}() {{").unwrap_or(());
+                writeln!(result, "    local context=0 word").unwrap_or(());
+                writeln!(result, "    for word in \"${{(@)words[2,CURRENT-1]}}\"; do")
+                    .unwrap_or(());
+                write_transitions(&mut result, &templates);
+                writeln!(result, "    done").unwrap_or(());
+                writeln!(result, "    case \"${{context}}\" in").unwrap_or(());
+                write_candidates(&mut result, self, |words| format!("compadd -- {words}"));
+                writeln!(result, "    esac").unwrap_or(());
+                writeln!(result, "}}").unwrap_or(());
+                writeln!(result).unwrap_or(());
+                write!(result, "_{function} \"$@\"").unwrap_or(());
+            }
+        }
+
+        result
+    }
+}
+
+/// Write a `case` statement, which switches the current context the same way `Parser::parse`
+/// does. Subcommands are matched first, top-level commands match in any context.
+fn write_transitions(result: &mut String, templates: &[&Template]) {
+    writeln!(result, "        case \"${{context}}#${{word}}\" in").unwrap_or(());
+    let (sub, top): (Vec<&Template>, Vec<&Template>) =
+        templates.iter().partition(|t| t.subargument_of.is_some());
+    for template in sub.iter().chain(top.iter()) {
+        let patterns: Vec<String> = template
+            .matches
+            .iter()
+            .map(|m| match template.subargument_of {
+                Some(parent) => quote(&format!("{parent}#{m}")),
+                None => format!("*{}", quote(&format!("#{m}"))),
+            })
+            .collect();
+        writeln!(
+            result,
+            "            {}) context={} ;;",
+            patterns.join("|"),
+            template.id
+        )
+        .unwrap_or(());
+    }
+    writeln!(result, "        esac").unwrap_or(());
+}
+
+/// Write the arms of a `case` statement, which offer the subcommands of each context together
+/// with the top-level commands.
+fn write_candidates<F: Fn(&str) -> String>(result: &mut String, parser: &Parser, f: F) {
+    let templates = parser.unique_templates();
+    let words = |parent: Option<usize>| -> Vec<String> {
+        templates
+            .iter()
+            .filter(|t| t.subargument_of == parent)
+            .flat_map(|t| t.matches.iter().map(|m| m.to_string()))
+            .collect()
+    };
+
+    let mut top = words(None);
+    if parser.auto_help {
+        top.extend(parser.help_flags.iter().cloned());
+    }
+    if parser.version.is_some() {
+        top.extend(parser.version_flags.iter().cloned());
+    }
+
+    for template in templates.iter() {
+        let children = words(Some(template.id));
+        if children.is_empty() {
+            continue;
+        }
+        let all: Vec<String> = children
+            .iter()
+            .chain(top.iter())
+            .map(|w| quote(w))
+            .collect();
+        writeln!(result, "        {}) {} ;;", template.id, f(&all.join(" "))).unwrap_or(());
+    }
+
+    let all: Vec<String> = top.iter().map(|w| quote(w)).collect();
+    writeln!(result, "        *) {} ;;", f(&all.join(" "))).unwrap_or(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Parser, Shell};
+
+    #[test]
+    fn completion() {
+        let mut parser = Parser::new().with_program_name("my-prog");
+        parser.add("--say", 1, "Say something.");
+        let count = parser.add("-c", 0, "Count something.");
+        parser
+            .add_subcommand(count, "--add", 2, "Add two numbers.")
+            .unwrap();
+
+        let bash = parser.generate_completion(Shell::Bash);
+        assert!(bash.starts_with("_my_prog() {"));
+        assert!(bash.contains(&format!("'{count}#--add') context=")));
+        assert!(bash.contains(&format!("*'#-c') context={count} ;;")));
+        assert!(bash.contains(&format!(
+            "{count}) COMPREPLY=($(compgen -W \"'--add' '--say'"
+        )));
+        assert!(bash.ends_with("complete -F _my_prog 'my-prog'"));
+
+        let zsh = parser.generate_completion(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef my-prog"));
+        assert!(zsh.contains("*) compadd -- '--say' '-c' '-h' '--help' ;;"));
+    }
+}
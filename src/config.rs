@@ -0,0 +1,76 @@
+//! Module containing the parser of configuration files, see `Parser::with_config_file()`.
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::HpError;
+
+/// Read the configuration file at `path`, a missing file is an empty configuration.
+pub(crate) fn read(path: &Path) -> Result<HashMap<String, String>, HpError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse(&contents),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(HpError::ConfigParseError(0, e.to_string())),
+    }
+}
+
+/// Parse `key = value` lines, ignoring empty lines and comments starting with `#` or `;`.
+///
+/// Values may be enclosed in double quotes, to keep the surrounding whitespace.
+fn parse(contents: &str) -> Result<HashMap<String, String>, HpError> {
+    let mut result = HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(HpError::ConfigParseError(
+                number + 1,
+                "expected 'key = value'".into(),
+            ));
+        };
+        let key = key.trim();
+        if key.is_empty() || key.chars().any(char::is_whitespace) {
+            return Err(HpError::ConfigParseError(
+                number + 1,
+                format!("invalid key '{key}'"),
+            ));
+        }
+        let value = value.trim();
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => quoted.strip_suffix('"').ok_or_else(|| {
+                HpError::ConfigParseError(number + 1, "unterminated quote".into())
+            })?,
+            None => value,
+        };
+
+        result.insert(key.to_string(), value.to_string());
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        let config = parse("# comment\n\nname = hp\n; other\nsay = \" hi \"\nempty =\n").unwrap();
+        assert_eq!(config["name"], "hp");
+        assert_eq!(config["say"], " hi ");
+        assert_eq!(config["empty"], "");
+
+        assert_eq!(
+            parse("name = hp\nbroken"),
+            Err(HpError::ConfigParseError(
+                2,
+                "expected 'key = value'".into()
+            ))
+        );
+        assert!(parse("a b = c").is_err());
+        assert!(parse("a = \"c").is_err());
+    }
+}
@@ -1,25 +1,248 @@
 //! Module contaning the errors which my arise when parsing.
+//!
+//! Errors are colored, when the standard error output is a terminal. This can be overridden with
+//! `set_color()`.
 use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 const RED: &str = "\x1b[31m";
 const GREEN: &str = "\x1b[32m";
 const YELLOW: &str = "\x1b[33m";
 const NONE: &str = "\x1b[0m";
 
+const COLOR_AUTO: u8 = 0;
+const COLOR_ALWAYS: u8 = 1;
+const COLOR_NEVER: u8 = 2;
+
+static COLOR: AtomicU8 = AtomicU8::new(COLOR_AUTO);
+
+/// The position of values, which were not in the command line, but taken from a fallback, such
+/// as an environment variable.
+pub(crate) const NO_POSITION: usize = usize::MAX;
+
+/// Enable or disable colors in the error messages.
+///
+/// If this function is never called, colors are used only when the standard error output is a
+/// terminal.
+pub fn set_color(enabled: bool) {
+    let value = if enabled { COLOR_ALWAYS } else { COLOR_NEVER };
+    COLOR.store(value, Ordering::Relaxed);
+}
+
+/// Return the red, green, yellow and reset escape codes, or empty strings if colors are disabled.
+pub(crate) fn colors(plain: bool) -> (&'static str, &'static str, &'static str, &'static str) {
+    let enabled = match COLOR.load(Ordering::Relaxed) {
+        COLOR_ALWAYS => true,
+        COLOR_NEVER => false,
+        _ => std::io::stderr().is_terminal(),
+    };
+
+    if enabled && !plain {
+        (RED, GREEN, YELLOW, NONE)
+    } else {
+        ("", "", "", "")
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 /// Enum type containing the errors.
 pub enum HpError {
     /// This error is caused by an insufficient number of values for an argument.
-    NumberOfValues(String, usize, usize),
+    ///
+    /// The last field is the zero-based position of the argument in the command, or `usize::MAX`,
+    /// if the values were taken from the configuration file, the environment or a prompt.
+    NumberOfValues(String, usize, usize, usize),
+    /// This error is caused by a number of values for an argument outside of its allowed range.
+    ///
+    /// The last field is the zero-based position of the argument in the command, or `usize::MAX`,
+    /// if the values were taken from the configuration file, the environment or a prompt.
+    NumberOfValuesRange(String, usize, usize, usize, usize),
+    /// This error is caused by more values following an argument than it takes, if it requires the
+    /// exact number of values, see `Template::exact_values()`.
+    ///
+    /// The last field is the zero-based position of the argument in the command.
+    TooManyValues(String, usize, usize, usize),
+    /// This error is caused by a known argument in place of a value of the preceding argument.
+    ///
+    /// Contains the argument missing its value and the argument found instead. The last field is
+    /// the zero-based position of the argument missing its value in the command.
+    ValueIsFlag(String, String, usize),
     /// This error is caused by passing a subcommand before passing its parent command.
-    OutOfContext(String, String),
+    ///
+    /// The last field is the zero-based position of the argument in the command.
+    OutOfContext(String, String, usize),
+    /// This error is caused by a value which could not be converted to the requested type.
+    ParseFailed(String, String),
+    /// This error is caused by a value which is not a decimal, hexadecimal, octal or binary integer.
+    InvalidInteger(String),
+    /// This error is caused by requesting a value at an index the argument does not have.
+    IndexOutOfRange(usize, usize),
+    /// This error is caused by an argument which does not match any template, in strict mode.
+    ///
+    /// Contains the closest known argument, if there is one similar enough, and the zero-based
+    /// position of the argument in the command.
+    UnknownArgument(String, Option<String>, usize),
+    /// This error is caused by required arguments which were not present in the command.
+    MissingRequired(Vec<String>),
+    /// This error is caused by a value which is not one of the possible values of an argument.
+    InvalidChoice(String, String, Vec<String>),
+    /// This error is caused by more than one argument of a mutually exclusive group being present.
+    ConflictingArguments(Vec<String>),
+    /// This error is caused by none of the arguments of a required group being present.
+    MissingOneOf(Vec<String>),
+    /// This error is caused by an abbreviated argument, which matches more than one template.
+    AmbiguousArgument(String, Vec<String>),
+    /// This error is caused by a response file, which could not be read.
+    ResponseFileError(String, String),
+    /// This error is caused by a line of a configuration file, which could not be parsed.
+    ///
+    /// Contains the number of the line, starting at 1, or 0, if the file could not be read.
+    ConfigParseError(usize, String),
+    /// This error is caused by a value of a key-value argument, which is not a `KEY=VALUE` pair.
+    MalformedKeyValue(String),
+    /// This error is caused by an action of an argument, which rejected its values.
+    ActionFailed(String, String),
+    /// This error is caused by a value rejected by the validator of an argument, see
+    /// `Template::validate_each()`.
+    ///
+    /// Contains the argument, the value and the message of the validator.
+    ValueValidationFailed(String, String, String),
+    /// This error is caused by a quote without a closing quote, in a line parsed with
+    /// `Parser::parse_str()`.
+    ///
+    /// Contains the whole line.
+    UnterminatedQuote(String),
+    /// This error is caused by an alias of a template, which is empty or contains whitespace, so
+    /// it can never be matched.
+    InvalidMatchName(String),
+    /// This error is caused by more than one template set to take the rest of the arguments.
+    MultipleRest(Vec<String>),
+    /// This error is caused by two templates sharing the same alias in the same context.
+    DuplicateMatch(String),
+    /// This error is caused by a subcommand of a template ID, which does not exist.
+    UnknownParent(usize),
+    /// This is not an error, the help message was requested and the parser is not set to exit.
+    ///
+    /// Contains the help message, which is also what this variant displays as.
+    HelpRequested(String),
+    /// This is not an error, the version was requested and the parser is not set to exit.
+    ///
+    /// Contains the version string, which is also what this variant displays as.
+    VersionRequested(String),
+}
+
+impl HpError {
+    /// Format the error without any colors, regardless of the `set_color()` setting.
+    ///
+    /// This is the same as formatting the error with the alternate flag, `format!("{err:#}")`.
+    pub fn to_string_plain(&self) -> String {
+        format!("{self:#}")
+    }
+
+    /// Return the zero-based position of the argument, which caused this error, if it is known.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Self::NumberOfValues(.., position)
+            | Self::NumberOfValuesRange(.., position)
+            | Self::TooManyValues(.., position)
+            | Self::ValueIsFlag(.., position)
+            | Self::OutOfContext(.., position)
+            | Self::UnknownArgument(.., position) => Some(*position).filter(|p| *p != NO_POSITION),
+            _ => None,
+        }
+    }
+
+    /// Format the error followed by the command line, with the argument which caused the error
+    /// underlined, if its position is known.
+    ///
+    /// `args` should be the parsed arguments, without the name of the program.
+    ///
+    /// ```ignore
+    /// // $ myprog --say hi --vebose
+    /// // ERROR: Unknown argument '--vebose' at position 2, did you mean '--verbose'?
+    /// //     --say hi --vebose
+    /// //              ^^^^^^^^
+    /// let args: Vec<String> = std::env::args().skip(1).collect();
+    /// if let Err(err) = parser.parse_from(args.clone()) {
+    ///     eprintln!("{}", err.pretty(&args));
+    /// }
+    /// ```
+    pub fn pretty(&self, args: &[String]) -> String {
+        let Some(position) = self.position().filter(|p| *p < args.len()) else {
+            return self.to_string();
+        };
+        let (red, _, _, none) = colors(false);
+        let offset: usize = args[..position].iter().map(|a| a.chars().count() + 1).sum();
+        let carets = "^".repeat(args[position].chars().count().max(1));
+
+        format!(
+            "{self}\n    {}\n    {}{red}{carets}{none}",
+            args.join(" "),
+            " ".repeat(offset)
+        )
+    }
+}
+
+/// Describe the position of an argument in an error message, if the argument was in the command
+/// line.
+fn at_position(position: usize) -> String {
+    match position {
+        NO_POSITION => String::new(),
+        position => format!(" at position {position}"),
+    }
 }
 
 impl Display for HpError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (red, green, yellow, none) = colors(f.alternate());
         match self {
-            Self::NumberOfValues(arg, got, expected) => write!(f, "{RED}ERROR{NONE}: In argument '{RED}{arg}{NONE}', expected '{GREEN}{expected}{NONE}' value/s, received '{YELLOW}{got}{NONE}'."),
-            Self::OutOfContext(arg, parent) => write!(f, "{RED}ERROR{NONE}: Out of context argument, because '{YELLOW}{arg}{NONE}' is a subcommand of '{GREEN}{parent}{NONE}' and '{GREEN}{parent}{NONE}' is not present in the command."),
+            Self::NumberOfValues(arg, got, expected, position) => write!(f, "{red}ERROR{none}: In argument '{red}{arg}{none}'{}, expected '{green}{expected}{none}' value/s, received '{yellow}{got}{none}'.", at_position(*position)),
+            Self::NumberOfValuesRange(arg, got, min, max, position) => write!(f, "{red}ERROR{none}: In argument '{red}{arg}{none}'{}, expected '{green}{min}{none}' to '{green}{max}{none}' value/s, received '{yellow}{got}{none}'.", at_position(*position)),
+            Self::TooManyValues(arg, got, expected, position) => write!(f, "{red}ERROR{none}: In argument '{red}{arg}{none}' at position {position}, expected '{green}{expected}{none}' value/s, but '{yellow}{got}{none}' follow."),
+            Self::ValueIsFlag(arg, flag, position) => write!(f, "{red}ERROR{none}: Argument '{red}{arg}{none}'{} expected a value, but found the argument '{yellow}{flag}{none}'.", at_position(*position)),
+            Self::OutOfContext(arg, parent, position) => write!(f, "{red}ERROR{none}: Out of context argument at position {position}, because '{yellow}{arg}{none}' is a subcommand of '{green}{parent}{none}' and '{green}{parent}{none}' is not present in the command."),
+            Self::ParseFailed(value, type_name) => write!(f, "{red}ERROR{none}: Failed to parse value '{yellow}{value}{none}' as '{green}{type_name}{none}'."),
+            Self::InvalidInteger(value) => write!(f, "{red}ERROR{none}: Failed to parse value '{yellow}{value}{none}' as an integer."),
+            Self::IndexOutOfRange(index, len) => write!(f, "{red}ERROR{none}: Value index '{yellow}{index}{none}' is out of range, the argument has '{green}{len}{none}' value/s."),
+            Self::UnknownArgument(arg, None, position) => write!(f, "{red}ERROR{none}: Unknown argument '{yellow}{arg}{none}' at position {position}."),
+            Self::UnknownArgument(arg, Some(suggestion), position) => write!(f, "{red}ERROR{none}: Unknown argument '{yellow}{arg}{none}' at position {position}, did you mean '{green}{suggestion}{none}'?"),
+            Self::MissingRequired(args) => {
+                let args: Vec<String> = args.iter().map(|arg| format!("'{yellow}{arg}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: Missing required argument/s {}.", args.join(", "))
+            }
+            Self::InvalidChoice(arg, got, allowed) => {
+                let allowed: Vec<String> = allowed.iter().map(|value| format!("'{green}{value}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: Invalid value '{yellow}{got}{none}' for argument '{red}{arg}{none}', expected one of {}.", allowed.join(", "))
+            }
+            Self::ConflictingArguments(args) => {
+                let args: Vec<String> = args.iter().map(|arg| format!("'{yellow}{arg}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: Arguments {} can not be used together.", args.join(", "))
+            }
+            Self::MissingOneOf(args) => {
+                let args: Vec<String> = args.iter().map(|arg| format!("'{green}{arg}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: One of the arguments {} is required.", args.join(", "))
+            }
+            Self::AmbiguousArgument(arg, candidates) => {
+                let candidates: Vec<String> = candidates.iter().map(|c| format!("'{green}{c}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: Ambiguous argument '{yellow}{arg}{none}', it could be any of {}.", candidates.join(", "))
+            }
+            Self::ResponseFileError(path, message) => write!(f, "{red}ERROR{none}: Failed to read response file '{yellow}{path}{none}': {message}."),
+            Self::ConfigParseError(0, message) => write!(f, "{red}ERROR{none}: Failed to read the configuration file: {message}."),
+            Self::ConfigParseError(line, message) => write!(f, "{red}ERROR{none}: Failed to parse the configuration file at line {yellow}{line}{none}: {message}."),
+            Self::MalformedKeyValue(value) => write!(f, "{red}ERROR{none}: Malformed value '{yellow}{value}{none}', expected '{green}KEY=VALUE{none}'."),
+            Self::ActionFailed(arg, message) => write!(f, "{red}ERROR{none}: In argument '{red}{arg}{none}': {message}."),
+            Self::ValueValidationFailed(arg, value, message) => write!(f, "{red}ERROR{none}: Invalid value '{yellow}{value}{none}' of argument '{red}{arg}{none}': {message}."),
+            Self::UnterminatedQuote(line) => write!(f, "{red}ERROR{none}: Unterminated quote in '{yellow}{line}{none}'."),
+            Self::InvalidMatchName(name) => write!(f, "{red}ERROR{none}: Invalid argument name '{yellow}{name}{none}', names can not be empty or contain whitespace."),
+            Self::MultipleRest(args) => {
+                let args: Vec<String> = args.iter().map(|arg| format!("'{yellow}{arg}{none}'")).collect();
+                write!(f, "{red}ERROR{none}: Only one argument can take the rest of the arguments, but {} do.", args.join(", "))
+            }
+            Self::DuplicateMatch(arg) => write!(f, "{red}ERROR{none}: Argument '{yellow}{arg}{none}' is matched by more than one template in the same context."),
+            Self::UnknownParent(id) => write!(f, "{red}ERROR{none}: Subcommand of a template with ID '{yellow}{id}{none}', which does not exist."),
+            Self::HelpRequested(help) => write!(f, "{help}"),
+            Self::VersionRequested(version) => write!(f, "{version}"),
         }
     }
 }
@@ -35,4 +258,35 @@ impl std::error::Error for HpError {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty() {
+        set_color(false);
+        let args: Vec<String> = vec!["--say".into(), "hi".into(), "--vebose".into()];
+        let err = HpError::UnknownArgument("--vebose".into(), None, 2);
+        assert_eq!(
+            err.pretty(&args),
+            "ERROR: Unknown argument '--vebose' at position 2.\n    --say hi --vebose\n             ^^^^^^^^"
+        );
+        let err = HpError::ValueIsFlag("--say".into(), "-v".into(), 0);
+        assert_eq!(
+            err.pretty(&["--say".into(), "-v".into()]),
+            "ERROR: Argument '--say' at position 0 expected a value, but found the argument '-v'.\n    --say -v\n    ^^^^^"
+        );
+        assert_eq!(
+            HpError::MissingOneOf(vec!["-a".into()]).pretty(&args),
+            "ERROR: One of the arguments '-a' is required."
+        );
+    }
+
+    #[test]
+    fn plain() {
+        let err = HpError::UnknownArgument("--vebose".into(), Some("--verbose".into()), 2);
+        assert_eq!(
+            err.to_string_plain(),
+            "ERROR: Unknown argument '--vebose' at position 2, did you mean '--verbose'?"
+        );
+    }
+}
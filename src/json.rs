@@ -0,0 +1,42 @@
+//! Module containing helpers for writing JSON, without any dependencies.
+use std::fmt::Write;
+
+/// Quote and escape a string, so it is a valid JSON string.
+pub(crate) fn string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap_or(()),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+
+    result
+}
+
+/// Write a list of strings as a JSON array.
+pub(crate) fn array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|v| string(v)).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape() {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string("a \"b\" \\c"), "\"a \\\"b\\\" \\\\c\"");
+        assert_eq!(string("line\nnext\u{1}"), "\"line\\nnext\\u0001\"");
+        assert_eq!(array(&["a".into(), "b".into()]), "[\"a\",\"b\"]");
+        assert_eq!(array(&[]), "[]");
+    }
+}
@@ -56,7 +56,7 @@
 //!     // This ID can than be used to create subcommands for that given command.
 //!     let id = parser.add("-c", 0, "Count something based on the subcommands supplied.");
 //!
-//!     parser.add_subcommand(id, "--add", 2, "Perform addition on two numbers.");
+//!     parser.add_subcommand(id, "--add", 2, "Perform addition on two numbers.")?;
 //!
 //!
 //!     // $ myprog --add
@@ -81,7 +81,7 @@
 //!         .on_parse(|values| {
 //!             let (a, b): (i32, i32) = (values[0].parse().unwrap(), values[1].parse().unwrap());
 //!             println!("{}", a - b);
-//!         }));
+//!         }))?;
 //!
 //!     let presult = parser.parse(None);
 //!
@@ -103,31 +103,251 @@
 //! # Examples
 //! Most of the functions have some simple code examples. There's also an `examples` directory
 //! which currently shows the two main ways in which `hp` could be used.
+//!
+//! # Features
+//! - `sync`: store the actions of templates behind `Arc<Mutex>` instead of `Rc<RefCell>`, so that
+//!   `Parser` is `Send` and `Sync`. Closures passed to `Template::on_parse()` and the likes must
+//!   then be `Send`, see `MaybeSend`.
 
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env::{self, current_exe};
 use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::rc::Rc;
+use std::str::FromStr;
 
+pub use command::CommandBuilder;
+pub use completion::Shell;
 pub use errors::HpError;
 
+mod command;
+pub mod completion;
+mod config;
 pub mod errors;
+mod json;
+mod macros;
+mod manpage;
+mod shell;
+mod suggest;
+mod term;
+
+#[cfg(not(feature = "sync"))]
+type Shared<T> = std::rc::Rc<std::cell::RefCell<T>>;
+#[cfg(feature = "sync")]
+type Shared<T> = std::sync::Arc<std::sync::Mutex<T>>;
+
+/// A bound on the actions of templates, which is `Send` with the `sync` feature enabled and
+/// satisfied by every type otherwise.
+///
+/// With the `sync` feature, actions are stored behind an `Arc<Mutex>` instead of an
+/// `Rc<RefCell>`, which makes `Template` and `Parser` `Send` and `Sync`, so that a parser can be
+/// built on one thread and used on another. The closures passed to `on_parse()` and the likes
+/// must then be `Send` as well.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "sync"))]
+impl<T> MaybeSend for T {}
+
+/// A bound on the actions of templates, which is `Send`, because the `sync` feature is enabled.
+#[cfg(feature = "sync")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send> MaybeSend for T {}
+
+#[cfg(not(feature = "sync"))]
+type AnyValue = dyn std::any::Any;
+#[cfg(feature = "sync")]
+type AnyValue = dyn std::any::Any + Send;
+
+#[cfg(not(feature = "sync"))]
+type ActionFn = dyn FnMut(&str, TemplateId, Vec<String>) -> Result<(), String>;
+#[cfg(feature = "sync")]
+type ActionFn = dyn FnMut(&str, TemplateId, Vec<String>) -> Result<(), String> + Send;
+
+#[cfg(not(feature = "sync"))]
+type FoldFn = dyn FnMut(Option<Box<AnyValue>>, Vec<String>) -> Box<AnyValue>;
+#[cfg(feature = "sync")]
+type FoldFn = dyn FnMut(Option<Box<AnyValue>>, Vec<String>) -> Box<AnyValue> + Send;
+
+#[cfg(not(feature = "sync"))]
+type ValidateFn = dyn FnMut(&str) -> Result<(), String>;
+#[cfg(feature = "sync")]
+type ValidateFn = dyn FnMut(&str) -> Result<(), String> + Send;
+
+/// An optional closure of a `Template` or a `Parser`, such as an action, which is shown as
+/// `Some(<closure>)` or `None` when debugging and ignored when comparing templates.
+struct Callback<F: ?Sized>(Option<Shared<F>>);
+
+/// The action of a template, see `Template::on_parse()`.
+type Action = Callback<ActionFn>;
+/// The fold of a template, see `Template::fold()`.
+type Fold = Callback<FoldFn>;
+/// The validator of the values of a template, see `Template::validate_each()`.
+type Validator = Callback<ValidateFn>;
+
+impl<F: ?Sized> Callback<F> {
+    /// Call `f` with the closure, return `None` if there is no closure or it's already running.
+    fn with_inner<R>(&self, f: impl FnOnce(&mut F) -> R) -> Option<R> {
+        let inner = self.0.as_ref()?;
+
+        #[cfg(not(feature = "sync"))]
+        let mut inner = inner.try_borrow_mut().ok()?;
+        #[cfg(feature = "sync")]
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        Some(f(&mut *inner))
+    }
+}
+
+impl Action {
+    fn new<F>(f: F) -> Self
+    where
+        F: FnMut(&str, TemplateId, Vec<String>) -> Result<(), String> + MaybeSend + 'static,
+    {
+        let shared: Shared<ActionFn> = shared(f);
+        Self(Some(shared))
+    }
+
+    /// Call the action, if there is one.
+    fn call(&self, name: &str, id: TemplateId, values: Vec<String>) -> Result<(), String> {
+        if self.0.is_none() {
+            return Ok(());
+        }
+
+        self.with_inner(|action| action(name, id, values))
+            .unwrap_or_else(|| Err("the action is already running".to_string()))
+    }
+}
+
+impl Fold {
+    fn new<T, F>(initial: T, f: F) -> Self
+    where
+        T: Clone + MaybeSend + 'static,
+        F: Fn(T, Vec<String>) -> T + MaybeSend + 'static,
+    {
+        let step = move |state: Option<Box<AnyValue>>, values| -> Box<AnyValue> {
+            let state = match state.map(|state| state.downcast::<T>()) {
+                Some(Ok(state)) => *state,
+                _ => initial.clone(),
+            };
+            Box::new(f(state, values))
+        };
+
+        let shared: Shared<FoldFn> = shared(step);
+        Self(Some(shared))
+    }
+
+    /// Fold `values` into the previous state, if there is a fold.
+    fn call(&self, state: Option<Box<AnyValue>>, values: &[String]) -> Option<Box<AnyValue>> {
+        self.with_inner(|fold| fold(state, values.to_vec()))
+    }
+}
+
+impl Validator {
+    fn new<F: Fn(&str) -> Result<(), String> + MaybeSend + 'static>(f: F) -> Self {
+        let shared: Shared<ValidateFn> = shared(f);
+        Self(Some(shared))
+    }
+
+    /// Validate `value`, if there is a validator.
+    fn call(&self, value: &str) -> Result<(), String> {
+        self.with_inner(|validator| validator(value))
+            .unwrap_or(Ok(()))
+    }
+}
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> Default for Callback<F> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<F: ?Sized> std::fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Some(<closure>)"),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+impl<F: ?Sized> PartialEq for Callback<F> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
 
-type Action = Rc<RefCell<dyn FnMut(Vec<String>)>>;
 pub type TemplateId = usize;
 
+/// The key of a template or a parsed argument, the context it belongs to and its name.
+type Key = (usize, String);
+
+/// The context of the keys of global templates, see `Template::global()`.
+const GLOBAL: usize = usize::MAX;
+
 #[derive(Clone, Debug)]
 /// A parsed and verified .
 pub struct ParsedArgument {
     id: TemplateId,
     values: Vec<String>,
+    supplied: bool,
+    negated: bool,
+    matched: String,
+    parent: Option<TemplateId>,
 }
 
 impl ParsedArgument {
     fn new(id: usize, values: Vec<String>) -> Self {
-        Self { id, values }
+        Self {
+            id,
+            supplied: !values.is_empty(),
+            negated: false,
+            matched: String::new(),
+            parent: None,
+            values,
+        }
+    }
+
+    /// Return `true`, if this parsed argument has no values, not even default ones.
+    ///
+    /// Together with `ParsedArguments::has()`, this tells a flag passed without a value from an
+    /// absent flag, which is useful for templates with optional values.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--color").number_of_values(1).optional_values(true));
+    ///
+    /// let color = match result.get("--color") {
+    ///     None => "never",                          // $ myprog
+    ///     Some(arg) if arg.is_empty() => "auto",    // $ myprog --color
+    ///     Some(arg) => arg.values()[0].as_str(),    // $ myprog --color always
+    /// };
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Return `true`, if the values of this argument were supplied, either in the command or
+    /// through an environment variable, and `false`, if they are the default values or there are
+    /// none.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--level").number_of_values(1)
+    ///     .optional_values(true).default_value("1"));
+    ///
+    /// // $ myprog --level
+    /// let level = result.get("--level").unwrap();
+    /// assert_eq!(level.values(), &vec!["1"]);
+    /// assert!(!level.was_supplied_value());
+    /// ```
+    pub fn was_supplied_value(&self) -> bool {
+        self.supplied
     }
 
     /// Return the parsed argument values.
@@ -140,10 +360,103 @@ impl ParsedArgument {
         self.id
     }
 
+    /// Return the alias of the template, as it was typed in the command line arguments.
+    ///
+    /// This is the first alias of the template, if the values were taken from an environment
+    /// variable.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("-x").matches("--expand"));
+    /// let result = parser.parse(None)?;
+    ///
+    /// // $ myprog --expand
+    /// assert_eq!(result.get("--expand").unwrap().matched(), "--expand");
+    /// ```
+    pub fn matched(&self) -> &str {
+        &self.matched
+    }
+
     /// Get the number of values this parsed argument has.
     pub fn number_of_values(&self) -> usize {
         self.values.len()
     }
+
+    /// Parse the value at `index` into any type implementing `FromStr`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--port", 1, "Port to listen on.");
+    /// let result = parser.parse(None)?;
+    ///
+    /// if let Some(port) = result.get("--port") {
+    ///     let port: u16 = port.value_as(0)?;
+    /// }
+    /// ```
+    pub fn value_as<T: FromStr>(&self, index: usize) -> Result<T, HpError> {
+        let value = self
+            .values
+            .get(index)
+            .ok_or(HpError::IndexOutOfRange(index, self.values.len()))?;
+
+        value.parse().map_err(|_| {
+            HpError::ParseFailed(value.to_string(), std::any::type_name::<T>().to_string())
+        })
+    }
+
+    /// Parse all the values of this argument into any type implementing `FromStr`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--add", 2, "Add two numbers.");
+    /// let result = parser.parse(None)?;
+    ///
+    /// if let Some(add) = result.get("--add") {
+    ///     let numbers: Vec<i32> = add.values_as()?;
+    ///     println!("{}", numbers.iter().sum::<i32>());
+    /// }
+    /// ```
+    pub fn values_as<T: FromStr>(&self) -> Result<Vec<T>, HpError> {
+        (0..self.values.len()).map(|i| self.value_as(i)).collect()
+    }
+
+    /// Parse the value at `index` as an integer, accepting the `0x`, `0o` and `0b` prefixes.
+    ///
+    /// Values without a prefix are parsed as decimal, a leading sign is allowed in all cases.
+    ///
+    /// ```ignore
+    /// // $ myprog --mask 0xFF
+    /// let mask = result.get("--mask").unwrap().int_value(0)?;
+    /// assert_eq!(mask, 255);
+    /// ```
+    pub fn int_value(&self, index: usize) -> Result<i64, HpError> {
+        let value = self
+            .values
+            .get(index)
+            .ok_or(HpError::IndexOutOfRange(index, self.values.len()))?;
+
+        let (sign, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", value.strip_prefix('+').unwrap_or(value)),
+        };
+        let prefix = unsigned.get(..2).map(str::to_ascii_lowercase);
+        let (radix, digits) = match prefix.as_deref() {
+            Some("0x") => (16, &unsigned[2..]),
+            Some("0o") => (8, &unsigned[2..]),
+            Some("0b") => (2, &unsigned[2..]),
+            _ => (10, unsigned),
+        };
+
+        if digits.starts_with(['+', '-']) {
+            return Err(HpError::InvalidInteger(value.to_string()));
+        }
+        i64::from_str_radix(&format!("{sign}{digits}"), radix)
+            .map_err(|_| HpError::InvalidInteger(value.to_string()))
+    }
+
+    /// Serialize the values of this argument to a JSON array, `["v1","v2"]`.
+    pub fn to_json(&self) -> String {
+        json::array(&self.values)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -153,13 +466,112 @@ impl ParsedArgument {
 /// provides an interface for the user to quickly and simply verify the presence of a
 /// `ParsedArgument` and additionally retrieve its values.
 pub struct ParsedArguments {
-    hm: HashMap<String, ParsedArgument>,
+    hm: HashMap<Key, ParsedArgument>,
+    aliases: HashMap<Key, TemplateId>,
     ids: HashMap<usize, ParsedArgument>,
+    counts: HashMap<usize, usize>,
+    trailing: Vec<String>,
+    positionals: Vec<String>,
+    unknown: Vec<String>,
+    subcommand: Option<String>,
+    folded: HashMap<TemplateId, Shared<Box<AnyValue>>>,
+    stats: ParseStats,
+    normalized: bool,
+}
+
+/// A type, which can be created from parsed arguments, with `ParsedArguments::extract()`.
+///
+/// ```ignore
+/// struct Opts {
+///     verbose: bool,
+///     output: String,
+///     jobs: u32,
+/// }
+///
+/// impl FromParsed for Opts {
+///     fn from_parsed(result: &ParsedArguments) -> Result<Self, HpError> {
+///         Ok(Self {
+///             verbose: result.has("--verbose"),
+///             output: result.require_as("--output")?,
+///             jobs: result.get_as("--jobs")?.unwrap_or(1),
+///         })
+///     }
+/// }
+///
+/// let opts: Opts = parser.parse(None)?.extract()?;
+/// ```
+pub trait FromParsed: Sized {
+    /// Create `Self` from the parsed arguments, or return an error, such as
+    /// `HpError::MissingRequired`.
+    fn from_parsed(result: &ParsedArguments) -> Result<Self, HpError>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+/// The order of the arguments in the help message, see `Parser::sort_help()`.
+///
+/// Subcommands are always listed under their parent and the help and version flags are always
+/// last in the "Arguments:" section.
+pub enum SortMode {
+    /// Arguments in the order in which they were added, named groups in the order of their first
+    /// argument.
+    #[default]
+    Insertion,
+    /// Arguments sorted by their first alias, ignoring the leading dashes and the case, named
+    /// groups in the order of their first argument.
+    Alphabetical,
+    /// Arguments in the order in which they were added, named groups sorted by their names.
+    Grouped,
+}
+
+#[derive(Clone, Debug)]
+/// A node of the tree of the parsed arguments, see `ParsedArguments::tree()`.
+pub struct ArgNode {
+    argument: Option<ParsedArgument>,
+    children: Vec<ArgNode>,
+}
+
+impl ArgNode {
+    /// Return the parsed argument of this node, `None` for the root of the tree.
+    pub fn argument(&self) -> Option<&ParsedArgument> {
+        self.argument.as_ref()
+    }
+
+    /// Return the name of the argument, as it was matched, empty for the root of the tree.
+    pub fn name(&self) -> &str {
+        self.argument.as_ref().map_or("", |a| a.matched())
+    }
+
+    /// Return the values of the argument, empty for the root of the tree.
+    pub fn values(&self) -> &[String] {
+        self.argument.as_ref().map_or(&[], |a| a.values())
+    }
+
+    /// Return the parsed subcommands of the argument, in the order in which their templates were
+    /// added. The children of the root are the parsed top-level arguments.
+    pub fn children(&self) -> &[ArgNode] {
+        &self.children
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+/// Statistics about a single parse, see `ParsedArguments::stats()`.
+pub struct ParseStats {
+    /// The number of arguments consumed by templates, either as their names or as their values.
+    pub tokens_consumed: usize,
+    /// The number of times any template was matched.
+    pub matched_count: usize,
+    /// The number of ancestors of the most deeply nested matched subcommand, zero if only top
+    /// level templates were matched.
+    pub max_context_depth: usize,
 }
 
 impl ParsedArguments {
     /// Try to get a **top-level** parsed argument, given its name.
     ///
+    /// Any alias of the template can be used, regardless of the alias in the command line. An
+    /// alias, which was in the command line, returns its own last occurrence, any other alias
+    /// the last occurrence of the template.
+    ///
     /// ```ignore
     /// let mut parser = Parser::new();
     /// parser.add("--some-arg", 0, "Some help.");
@@ -168,8 +580,112 @@ impl ParsedArguments {
     /// result.get("--some-arg").is_some().then(|| println!("--some-arg in arguments!"))
     /// ```
     pub fn get(&self, key: impl AsRef<str>) -> Option<&ParsedArgument> {
-        let key = format!("0#{}", key.as_ref());
-        self.hm.get(&key)
+        self.get_with_context(0, key)
+    }
+
+    /// Get the first value of a **top-level** argument, or `default`, if the argument is not
+    /// present or has no values.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--name", 1, "Your name.");
+    /// let result = parser.parse()?;
+    ///
+    /// println!("Hello, {}!", result.get_or("--name", "world"));
+    /// ```
+    pub fn get_or(&self, key: impl AsRef<str>, default: &str) -> String {
+        self.get(key)
+            .and_then(|pa| pa.values.first().cloned())
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// Get a **top-level** parsed argument, which has to be present.
+    ///
+    /// Returns `HpError::MissingRequired`, if the argument is not present.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--input", 1, "The input file.");
+    /// let result = parser.parse()?;
+    ///
+    /// let input = result.require("--input")?;
+    /// ```
+    pub fn require(&self, key: impl AsRef<str>) -> Result<&ParsedArgument, HpError> {
+        self.get(key.as_ref())
+            .ok_or_else(|| HpError::MissingRequired(vec![key.as_ref().to_string()]))
+    }
+
+    /// Get the value with the given index of a **top-level** argument, which has to be present.
+    ///
+    /// Returns `HpError::MissingRequired`, if the argument is not present and
+    /// `HpError::IndexOutOfRange`, if it doesn't have the value.
+    ///
+    /// ```ignore
+    /// let input = result.require_value("--input", 0)?;
+    /// ```
+    pub fn require_value(&self, key: impl AsRef<str>, index: usize) -> Result<&str, HpError> {
+        let values = &self.require(key)?.values;
+        values
+            .get(index)
+            .map(|value| value.as_str())
+            .ok_or(HpError::IndexOutOfRange(index, values.len()))
+    }
+
+    /// Parse the first value of a **top-level** argument, which has to be present, see
+    /// `ParsedArgument::value_as()`.
+    ///
+    /// ```ignore
+    /// let port: u16 = result.require_as("--port")?;
+    /// ```
+    pub fn require_as<T: FromStr>(&self, key: impl AsRef<str>) -> Result<T, HpError> {
+        self.require(key)?.value_as(0)
+    }
+
+    /// Parse the first value of a **top-level** argument, if it's present, see
+    /// `ParsedArgument::value_as()`.
+    ///
+    /// ```ignore
+    /// let jobs: u32 = result.get_as("--jobs")?.unwrap_or(1);
+    /// ```
+    pub fn get_as<T: FromStr>(&self, key: impl AsRef<str>) -> Result<Option<T>, HpError> {
+        self.get(key).map(|arg| arg.value_as(0)).transpose()
+    }
+
+    /// Create a structure from these parsed arguments, see `FromParsed`.
+    pub fn extract<T: FromParsed>(&self) -> Result<T, HpError> {
+        T::from_parsed(self)
+    }
+
+    /// Get all the values of a **top-level** argument, or `default`, if the argument is not
+    /// present or has no values.
+    pub fn get_all_or(&self, key: impl AsRef<str>, default: Vec<String>) -> Vec<String> {
+        match self.get(key) {
+            Some(pa) if !pa.values.is_empty() => pa.values.clone(),
+            _ => default,
+        }
+    }
+
+    /// Collect the `KEY=VALUE` pairs of a **top-level** key-value argument into a map, see
+    /// `Template::key_value()`.
+    ///
+    /// The map is empty, if the argument is not present. Later occurrences of a key replace the
+    /// earlier ones.
+    ///
+    /// ```ignore
+    /// // $ myprog --define name=hp --define version=1.0
+    /// let defines = result.get_map("--define");
+    /// assert_eq!(defines.get("version"), Some(&"1.0".to_string()));
+    /// ```
+    pub fn get_map(&self, key: impl AsRef<str>) -> HashMap<String, String> {
+        let Some(arg) = self.get(key).and_then(|arg| self.ids.get(&arg.id)) else {
+            return HashMap::new();
+        };
+
+        arg.values
+            .iter()
+            .filter_map(|value| value.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
     }
 
     /// Try to get a parsed argument, given its ID.
@@ -200,6 +716,47 @@ impl ParsedArguments {
         self.get(key).is_some()
     }
 
+    /// Assert, whether the chain of subcommands `path` has been parsed, starting with a
+    /// **top-level** argument, each following argument being a subcommand of the previous one.
+    ///
+    /// An empty path is always present.
+    ///
+    /// ```ignore
+    /// let expand = parser.add("-x", 0, "Expand something.");
+    /// let string = parser.add_subcommand(expand, "--string", 0, "Expand a string.")?;
+    /// parser.add_subcommand(string, "--super-test", 0, "Amazing super test.")?;
+    /// let result = parser.parse()?;
+    ///
+    /// // $ myprog -x --string --super-test
+    /// assert!(result.has_path(&["-x", "--string", "--super-test"]));
+    /// ```
+    pub fn has_path(&self, path: &[&str]) -> bool {
+        let mut context = 0;
+        for name in path {
+            match self.get_with_context(context, name) {
+                Some(arg) => context = arg.id,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Return the state of a **top-level** negatable flag, `Some(true)` if it was set,
+    /// `Some(false)` if it was negated and `None`, if neither is present. The last occurrence
+    /// wins, see `Template::negatable()`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--color").negatable(true));
+    /// let result = parser.parse()?;
+    ///
+    /// // $ myprog --no-color
+    /// assert_eq!(result.flag("--color"), Some(false));
+    /// ```
+    pub fn flag(&self, key: impl AsRef<str>) -> Option<bool> {
+        self.get(key).map(|arg| !arg.negated)
+    }
+
     /// Assert, whether argument whith `id` has been parsed.
     ///
     /// This function is an alias for `parsed_args.get_with_id(id).is_some()`.
@@ -220,7 +777,7 @@ impl ParsedArguments {
     /// ```ignore
     /// let mut parser = Parser::new();
     /// let world = parser.add("--world", 0, "World command.");
-    /// parser.add_subcommand(world, "--new", 1, "Add a new world.")
+    /// parser.add_subcommand(world, "--new", 1, "Add a new world.")?;
     /// let result = parser.parse()?;
     ///
     /// if let Some(new_world) = result.get_with_context(world, "--new") {
@@ -232,8 +789,14 @@ impl ParsedArguments {
         context: usize,
         key: impl AsRef<str>,
     ) -> Option<&ParsedArgument> {
-        let key = format!("{context}#{}", key.as_ref());
-        self.hm.get(&key)
+        let key = match self.normalized {
+            true => strip_dashes(key.as_ref()),
+            false => key.as_ref(),
+        };
+        let key = (context, key.to_string());
+        self.hm
+            .get(&key)
+            .or_else(|| self.aliases.get(&key).and_then(|id| self.ids.get(id)))
     }
 
     /// Asssert, whether a subargument of an argument is present in the `ParsedArguments`.
@@ -243,7 +806,7 @@ impl ParsedArguments {
     /// ```ignore
     /// let mut parser = Parser::new();
     /// let world = parser.add("--world", 0, "World command.");
-    /// parser.add_subcommand(world, "--list", 0, "Add a new world.")
+    /// parser.add_subcommand(world, "--list", 0, "Add a new world.")?;
     /// let result = parser.parse()?;
     ///
     /// if result.has_with_context(world, "--list") {
@@ -253,722 +816,5711 @@ impl ParsedArguments {
     pub fn has_with_context(&self, context: usize, key: impl AsRef<str>) -> bool {
         self.get_with_context(context, key).is_some()
     }
-}
-
-#[derive(Default, Clone)]
-/// `Template` contains all the necessary information for matching and parsing a command line
-/// argument.
-pub struct Template {
-    matches: Vec<String>,
-    num_values: usize,
-    optional_vals: bool,
-    help: String,
-    subargument_of: Option<usize>,
-    id: TemplateId,
-    action: Option<Action>,
-}
 
-impl Template {
-    /// Creates a new `Template`.
+    /// Count how many times a **top-level** argument that matches `key` has been parsed.
     ///
-    /// Create an empty, uninitialized `Template`.
-    pub fn new() -> Self {
-        Self {
-            matches: Vec::new(),
-            num_values: 0,
-            optional_vals: false,
-            help: "".into(),
-            subargument_of: None,
-            id: 0,
-            action: None,
-        }
-    }
-
-    /// Add a value which identifies this template when parsing the command line arguments.
+    /// Occurrences of all the aliases of the argument's template are counted together, negations
+    /// of a negatable flag are not counted, see `Template::negatable()`. Returns 0, if the
+    /// argument is not present.
     ///
     /// ```ignore
-    /// parser.add_template(Template::new().matches("--hi"));
+    /// let mut parser = Parser::new();
+    /// parser.add_template(Template::new().matches("-v").matches("--verbose"));
+    /// let result = parser.parse()?;
     ///
-    /// let ret = parser.parse()?;
-    /// assert!(ret.has("--hi"))
+    /// // $ myprog -v -v --verbose
+    /// assert_eq!(result.count("-v"), 3);
     /// ```
-    pub fn matches<S: AsRef<str>>(mut self, name: S) -> Self {
-        let name = name.as_ref().to_string();
-        if !self.matches.contains(&name) {
-            self.matches.push(name)
-        }
-
-        self
+    pub fn count(&self, key: impl AsRef<str>) -> usize {
+        self.get(key).map_or(0, |pa| self.count_with_id(pa.id))
     }
 
-    /// Set the number of values this template takes.
+    /// Iterate over all the parsed arguments, in no particular order.
     ///
-    /// If 0, the argument takes no values, but it can have subarguments.
+    /// Each item contains the name of the argument, as it was last matched, and the parsed
+    /// argument. Every parsed template is yielded once, even if multiple of its aliases were used.
     ///
     /// ```ignore
-    /// parser.add_template(Template::new().matches("--say").number_of_values(1));
+    /// let result = parser.parse()?;
     ///
-    /// let ret = parser.parse()?;
-    /// if let Ok(vals) = ret.get("--say") {
-    ///     println!("{}", vals[0]);
+    /// for (name, arg) in result.iter() {
+    ///     println!("{name}: {:?}", arg.values());
     /// }
-    ///
-    /// // $ myprog --say hello
-    /// // hello
-    /// //
-    /// // $ myprog --say hello world
-    /// // hello
-    /// //
-    /// // $ myprog --say
-    /// // ERROR: missing argument for '--say'.
     /// ```
-    pub fn number_of_values(mut self, nv: usize) -> Self {
-        self.num_values = nv;
-        self
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ParsedArgument)> {
+        self.ids.values().map(|pa| (pa.matched(), pa))
     }
 
-    /// Ignores missing values when parsing command line arguments.
+    /// Serialize the parsed arguments to a JSON object, mapping the names of the arguments to
+    /// their values, `{"--flag":["v1","v2"]}`.
     ///
-    /// ```ignore
-    /// parser.add_template(Template::new()
-    ///                         .matches("--say")
-    ///                         .number_of_values(1)
-    ///                         .optional_values(true));
+    /// The names are sorted, so the output is the same for the same arguments.
+    pub fn to_json(&self) -> String {
+        let mut entries: Vec<(&str, &ParsedArgument)> = self.iter().collect();
+        entries.sort_by_key(|(name, _)| *name);
+
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|(name, pa)| format!("{}:{}", json::string(name), pa.to_json()))
+            .collect();
+        format!("{{{}}}", entries.join(","))
+    }
+
+    /// Return the number of parsed arguments, the same as the number of items yielded by
+    /// `iter()`.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Assert, whether no arguments were parsed.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Return all the arguments which came after the `--` separator.
     ///
-    /// let ret = parser.parse()?;
-    /// if let Ok(vals) = ret.get("--say") {
-    ///     if let Some(val) = vals.get(0) {
-    ///         println!("{val}");
-    ///     };
-    /// }
+    /// These arguments are never matched against templates, the separator itself is not
+    /// included.
     ///
-    /// // $ myprog --say hello
-    /// // hello
-    /// // $ myprog --say # nothing happens, would cause a missing argument error
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--file", 1, "Input file.");
+    /// let result = parser.parse()?;
+    ///
+    /// // $ myprog --file - -- --not-a-flag
+    /// assert_eq!(result.trailing(), &vec!["--not-a-flag"]);
     /// ```
-    pub fn optional_values(mut self, ov: bool) -> Self {
-        self.optional_vals = ov;
-        self
+    pub fn trailing(&self) -> &Vec<String> {
+        &self.trailing
     }
 
-    /// Set a help string for this template.
+    /// Return all the positional arguments, words which were neither matched by a template, nor
+    /// consumed as values of another argument.
+    ///
+    /// Unknown arguments starting with a dash are not positional arguments, with the exception
+    /// of a single `-`. Arguments after the `--` separator are available through `trailing()`.
     ///
     /// ```ignore
-    /// parser.with_description("My awesome program!")
-    /// parser.add_template(Template::new()
-    ///                         .matches("--say")
-    ///                         .number_of_values(1)
-    ///                         .optional_values(true)
-    ///                         .with_help("Print a given argument."));
+    /// let mut parser = Parser::new();
+    /// parser.add("build", 0, "Build the files.");
+    /// let result = parser.parse()?;
     ///
-    /// // ...
-    /// parser.parse()?;
+    /// // $ myprog build file1.rs file2.rs
+    /// assert_eq!(result.positionals(), &vec!["file1.rs", "file2.rs"]);
+    /// ```
+    pub fn positionals(&self) -> &Vec<String> {
+        &self.positionals
+    }
+
+    /// Return the arguments starting with a dash, which match no template, in the order in which
+    /// they were given, if `Parser::capture_unknown()` is set.
     ///
+    /// ```ignore
+    /// let mut parser = Parser::new().capture_unknown(true);
+    /// parser.add("--release", 0, "Build in release mode.");
+    /// let result = parser.parse(None)?;
     ///
-    /// // $ myprog --say hello
-    /// // hello
-    /// // $ myprog --help
-    /// // myprog: My awesome program!
-    /// // usage:
-    /// //     $ myprog -[-arguments] [value/s]
-    /// // arguments:
-    /// //     --say [1 optional value\s]        Print a given argument.
-    /// //     --help                          Print this help message.
+    /// // $ myprog --release -C opt-level=3 --emit=asm
+    /// assert_eq!(result.unknown(), &vec!["-C", "--emit=asm"]);
     /// ```
-    pub fn with_help<S: AsRef<str>>(mut self, help_string: S) -> Self {
-        self.help = help_string.as_ref().into();
-        self
+    pub fn unknown(&self) -> &Vec<String> {
+        &self.unknown
     }
 
-    /// Set an action that will be executed immediately when a command is parsed.
-    ///
-    /// This action is a function with the following signature: `fn(Vec<String>) -> ()`.
+    /// Return the command added by `Parser::command()`, which was matched.
     ///
     /// ```ignore
     /// let mut parser = Parser::new();
+    /// parser.command("push", "Update the remote refs.");
+    /// let result = parser.parse(None)?;
     ///
-    /// parser.add_subcommand_template(id, Template::new()
-    ///    .matches("--add")
-    ///    .number_of_values(2)
-    ///    .on_parse(|values| {
-    ///        let (a, b): (i32, i32) = (values[0].parse().unwrap(), values[1].parse().unwrap());
-    ///        println!("{}", a + b);
-    ///    }));
+    /// // $ myprog push
+    /// assert_eq!(result.subcommand(), Some("push"));
+    /// ```
+    pub fn subcommand(&self) -> Option<&str> {
+        self.subcommand.as_deref()
+    }
+
+    /// Return the value folded from all the occurrences of the template with the given ID, see
+    /// `Template::fold()`.
+    ///
+    /// Returns `None`, if the template is not present, has no fold, or if its folded value is not
+    /// a `T`.
+    pub fn folded<T: Clone + 'static>(&self, id: TemplateId) -> Option<T> {
+        let value = self.folded.get(&id)?;
+
+        #[cfg(not(feature = "sync"))]
+        let value = value.borrow();
+        #[cfg(feature = "sync")]
+        let value = value.lock().unwrap_or_else(|e| e.into_inner());
+
+        value.downcast_ref::<T>().cloned()
+    }
+
+    /// Return statistics about the parse, which produced these results.
     ///
+    /// ```ignore
+    /// // $ myprog db --url localhost extra
+    /// let stats = result.stats();
+    /// assert_eq!(stats.tokens_consumed, 3);
+    /// assert_eq!(stats.matched_count, 2);
+    /// assert_eq!(stats.max_context_depth, 1);
     /// ```
-    pub fn on_parse<F: FnMut(Vec<String>) + 'static>(mut self, action: F) -> Self {
-        self.action = Some(Rc::new(RefCell::new(action)));
-        self
+    pub fn stats(&self) -> ParseStats {
+        self.stats
     }
 
-    pub(crate) fn set_id(&mut self, id: usize) {
-        self.id = id
+    /// Count how many times an argument with `id` has been parsed.
+    ///
+    /// Returns 0, if the argument is not present.
+    pub fn count_with_id(&self, id: TemplateId) -> usize {
+        self.counts.get(&id).copied().unwrap_or(0)
     }
 
-    pub(crate) fn subarg(&mut self, id: usize) {
-        let _ = self.subargument_of.insert(id);
+    /// Return the parsed arguments as a tree mirroring the hierarchy of the subcommands. The root
+    /// has no argument and its children are the parsed top-level arguments.
+    ///
+    /// ```ignore
+    /// // $ myprog db --url localhost -v
+    /// let tree = result.tree();
+    /// let db = &tree.children()[0];
+    ///
+    /// assert_eq!(db.name(), "db");
+    /// assert_eq!(db.children()[0].values(), &["localhost"]);
+    /// ```
+    pub fn tree(&self) -> ArgNode {
+        ArgNode {
+            argument: None,
+            children: self.tree_children(None),
+        }
+    }
+
+    /// Create the nodes of the parsed arguments under `parent`, arguments of a parent, which
+    /// wasn't parsed, such as global templates, are children of the root.
+    fn tree_children(&self, parent: Option<TemplateId>) -> Vec<ArgNode> {
+        let mut arguments: Vec<&ParsedArgument> = self
+            .ids
+            .values()
+            .filter(|a| a.parent.filter(|p| self.ids.contains_key(p)) == parent)
+            .collect();
+        arguments.sort_by_key(|a| a.id);
+
+        arguments
+            .into_iter()
+            .map(|a| ArgNode {
+                argument: Some(a.clone()),
+                children: self.tree_children(Some(a.id)),
+            })
+            .collect()
     }
 }
 
-#[derive(Default, Clone)]
-/// Command line argument parser.
-///
-/// ```ignore
-/// let parser = Parser::new()
-///                 .exit_on_help(false)
-///                 .with_description("My amazing program!")
-///                 .with_usage("$ myprog [commmand] --[subcommand/s] [value/s]")
-///                 .with_author("[REDACTED]");
-/// ```
-pub struct Parser {
-    stored: HashMap<String, Template>,
-    order: Vec<String>,
-    last_id: usize,
-    exit_on_help: bool,
-    author: String,
-    description: String,
-    usage: String,
-    program_name: String,
-    help: Option<String>,
+#[derive(Default, Clone, PartialEq, Debug)]
+/// `Template` contains all the necessary information for matching and parsing a command line
+/// argument.
+pub struct Template {
+    matches: Vec<String>,
+    num_values: usize,
+    optional_vals: bool,
+    defaults: Vec<String>,
+    required: bool,
+    choices: Vec<String>,
+    ignore_case: bool,
+    env: Option<String>,
+    negative_numbers: bool,
+    dash_values: bool,
+    global: bool,
+    variadic: bool,
+    range: Option<(usize, usize)>,
+    at_least: Option<usize>,
+    group: Option<String>,
+    hidden: bool,
+    greedy: bool,
+    exact_values: bool,
+    stop_at: Vec<String>,
+    delimiter: Option<char>,
+    key_value: bool,
+    negatable: bool,
+    rest: bool,
+    deprecated: Option<String>,
+    prompt: Option<String>,
+    value_names: Vec<String>,
+    help: String,
+    subargument_of: Option<usize>,
+    id: TemplateId,
+    action: Action,
+    fold: Fold,
+    validator: Validator,
 }
 
-impl Parser {
-    /// Create a new `Parser` with default configuration, no author and description.
+impl Template {
+    /// Creates a new `Template`.
+    ///
+    /// Create an empty, uninitialized `Template`.
     pub fn new() -> Self {
-        let exe_name = match current_exe() {
-            Ok(pb) => {
-                if let Some(name) = pb.file_name() {
-                    name.to_str().unwrap_or("").to_string()
-                } else {
-                    "".to_string()
-                }
-            }
-            Err(_) => "".to_string(),
-        };
         Self {
-            stored: HashMap::new(),
-            order: Vec::new(),
-            last_id: 0,
-            exit_on_help: true,
-            author: "".to_string(),
-            description: "".to_string(),
-            usage: "".to_string(),
-            program_name: exe_name,
-            help: None,
+            matches: Vec::new(),
+            num_values: 0,
+            optional_vals: false,
+            defaults: Vec::new(),
+            required: false,
+            choices: Vec::new(),
+            ignore_case: false,
+            env: None,
+            negative_numbers: false,
+            dash_values: false,
+            global: false,
+            variadic: false,
+            range: None,
+            at_least: None,
+            group: None,
+            hidden: false,
+            greedy: false,
+            exact_values: false,
+            stop_at: Vec::new(),
+            delimiter: None,
+            key_value: false,
+            negatable: false,
+            rest: false,
+            deprecated: None,
+            prompt: None,
+            value_names: Vec::new(),
+            help: "".into(),
+            subargument_of: None,
+            id: 0,
+            action: Action::default(),
+            fold: Fold::default(),
+            validator: Validator::default(),
         }
     }
 
-    /// Specifies, whether the program should exit after printing the help message when the
-    /// '--help' or '-h' command line arguments are specified.
-    pub fn exit_on_help(mut self, v: bool) -> Self {
-        self.exit_on_help = v;
+    /// Add a value which identifies this template when parsing the command line arguments.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--hi"));
+    ///
+    /// let ret = parser.parse()?;
+    /// assert!(ret.has("--hi"))
+    /// ```
+    pub fn matches<S: AsRef<str>>(mut self, name: S) -> Self {
+        let name = name.as_ref().to_string();
+        if !self.matches.contains(&name) {
+            self.matches.push(name)
+        }
+
         self
     }
 
-    /// Specifies the author of the program, will be used when printing the help message.
-    pub fn with_author<S: AsRef<str>>(mut self, v: S) -> Self {
-        self.author = v.as_ref().to_string();
+    /// Set the number of values this template takes.
+    ///
+    /// If 0, the argument takes no values, but it can have subarguments.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--say").number_of_values(1));
+    ///
+    /// let ret = parser.parse()?;
+    /// if let Ok(vals) = ret.get("--say") {
+    ///     println!("{}", vals[0]);
+    /// }
+    ///
+    /// // $ myprog --say hello
+    /// // hello
+    /// //
+    /// // $ myprog --say hello world
+    /// // hello
+    /// //
+    /// // $ myprog --say
+    /// // ERROR: missing argument for '--say'.
+    /// ```
+    pub fn number_of_values(mut self, nv: usize) -> Self {
+        self.num_values = nv;
         self
     }
 
-    /// Specifies the description of the program, will be used when printing the help message.
-    pub fn with_description<S: AsRef<str>>(mut self, v: S) -> Self {
-        self.description = v.as_ref().to_string();
+    /// Make this template take any number of values, up to the next known argument or `--`.
+    ///
+    /// `number_of_values()` is ignored for variadic templates. Unless `optional_values()` is
+    /// set, at least one value is required.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("add").variadic(true));
+    ///
+    /// // $ myprog add 1 2 3 4
+    /// // 'add' has the values '1', '2', '3' and '4'.
+    /// ```
+    pub fn variadic(mut self, variadic: bool) -> Self {
+        self.variadic = variadic;
         self
     }
 
-    /// Specifies the usage of the program, will be used when printing the help message.
+    /// Make this template take at least `n` values, followed by any number of values up to the
+    /// next known argument or `--`.
     ///
-    /// If nothing is specified, `hp` will try to interpret the given templates and create a custom
-    /// usage string.
-    pub fn with_usage<S: AsRef<str>>(mut self, v: S) -> Self {
-        self.usage = v.as_ref().to_string();
+    /// This supersedes `number_of_values()` and `variadic()`. Fewer than `n` values are an error,
+    /// unless `optional_values()` is set.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("add").at_least(2));
+    ///
+    /// // $ myprog add 1 2 3
+    /// // 'add' has the values '1', '2' and '3'.
+    /// // $ myprog add 1
+    /// // ERROR: In argument 'add' at position 0, expected '2' value/s, received '1'.
+    /// ```
+    pub fn at_least(mut self, n: usize) -> Self {
+        self.at_least = Some(n);
         self
     }
 
-    /// Specifies the program name, will be used when printing the help message.
+    /// Make this template take between `min` and `max` values, inclusive.
     ///
-    /// If none is specified the name of the binary will be be used.
-    pub fn with_program_name<S: AsRef<str>>(mut self, v: S) -> Self {
-        self.program_name = v.as_ref().to_string();
+    /// This supersedes both `number_of_values()` and `variadic()`. Values are collected up to
+    /// `max`, fewer than `min` values are an error, unless `optional_values()` is set.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--files").values_range(1, 3));
+    ///
+    /// // $ myprog --files a b c d
+    /// // '--files' has the values 'a', 'b' and 'c'.
+    /// // $ myprog --files
+    /// // ERROR: In argument '--files', expected '1' to '3' value/s, received '0'.
+    /// ```
+    pub fn values_range(mut self, min: usize, max: usize) -> Self {
+        self.range = Some((min, max.max(min)));
         self
     }
 
-    /// Set a completely custom help string, which will be used when printing the `--help`
-    /// string.
-    pub fn set_help<S: AsRef<str>>(mut self, v: S) -> Self {
-        self.help = Some(v.as_ref().to_string());
+    /// Ignores missing values when parsing command line arguments.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--say")
+    ///                         .number_of_values(1)
+    ///                         .optional_values(true));
+    ///
+    /// let ret = parser.parse()?;
+    /// if let Ok(vals) = ret.get("--say") {
+    ///     if let Some(val) = vals.get(0) {
+    ///         println!("{val}");
+    ///     };
+    /// }
+    ///
+    /// // $ myprog --say hello
+    /// // hello
+    /// // $ myprog --say # nothing happens, would cause a missing argument error
+    /// ```
+    pub fn optional_values(mut self, ov: bool) -> Self {
+        self.optional_vals = ov;
         self
     }
 
-    fn generate_id(&mut self) -> usize {
-        self.last_id += 1;
-        self.last_id
+    /// Set a value which is used when the argument is present, but no value was supplied.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--color")
+    ///                         .number_of_values(1)
+    ///                         .optional_values(true)
+    ///                         .default_value("auto"));
+    ///
+    /// let ret = parser.parse()?;
+    /// if let Some(color) = ret.get("--color") {
+    ///     println!("{}", color.values()[0]);
+    /// }
+    ///
+    /// // $ myprog --color always
+    /// // always
+    /// // $ myprog --color
+    /// // auto
+    /// // $ myprog # nothing happens, the argument is not present
+    /// ```
+    pub fn default_value<S: AsRef<str>>(mut self, value: S) -> Self {
+        self.defaults = vec![value.as_ref().to_string()];
+        self
     }
 
-    fn add_to_map(&mut self, mut template: Template) -> TemplateId {
-        let template_id = self.generate_id();
-        template.set_id(template_id);
-        let matches = template.matches.clone();
-        for name in matches.iter() {
-            let subarg = template.subargument_of.unwrap_or(0);
-            let new_name = format!("{}#{}", subarg, name.clone());
-            let _ = self.stored.insert(new_name.clone(), template.clone());
-            self.order.push(name.clone())
-        }
+    /// Set multiple values which are used when the argument is present, but no value was
+    /// supplied.
+    pub fn default_values(mut self, values: Vec<String>) -> Self {
+        self.defaults = values;
+        self
+    }
 
-        template_id
+    /// Specifies, whether this template must be present in the command line arguments.
+    ///
+    /// A required subcommand is only required, when its parent command is present.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--output")
+    ///                         .number_of_values(1)
+    ///                         .required(true));
+    ///
+    /// // $ myprog
+    /// // ERROR: Missing required argument/s '--output'.
+    /// ```
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
     }
 
-    /// Add a new `Template` to the parser. Return the ID of the `Template`.
+    /// Set a prompt, which asks for the value of this required template, when it's missing.
     ///
-    /// This method is an abstraction, it creates the `Template` for you, but it takes away some of the options.
-    pub fn add<S: AsRef<str>>(
-        &mut self,
-        matches: S,
-        num_values: usize,
-        help_message: S,
-    ) -> TemplateId {
-        let template = Template::new()
-            .matches(matches)
-            .number_of_values(num_values)
-            .with_help(help_message);
-        self.add_to_map(template)
+    /// The prompt is printed to stderr and the value is read as a line from stdin, only if the
+    /// parser allows prompts, see `Parser::allow_prompts()`, and stdin is a terminal. Otherwise
+    /// the template is missing as usual.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().allow_prompts(true);
+    /// parser.add_template(Template::new()
+    ///                         .matches("--name")
+    ///                         .number_of_values(1)
+    ///                         .required(true)
+    ///                         .prompt("Your name: "));
+    /// ```
+    pub fn prompt<S: Into<String>>(mut self, prompt: S) -> Self {
+        self.prompt = Some(prompt.into());
+        self
     }
 
-    /// A a new `Template` to the parser. Return the ID of the `Template`.
+    /// Restrict the values of this template to a fixed set of choices.
     ///
-    /// Given a template, add it to the parser.
-    pub fn add_template(&mut self, template: Template) -> TemplateId {
-        self.add_to_map(template)
+    /// The choices are shown in the help message, any other value is reported as an error.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--color")
+    ///                         .number_of_values(1)
+    ///                         .possible_values(vec!["always".into(), "auto".into(), "never".into()]));
+    ///
+    /// // $ myprog --color sometimes
+    /// // ERROR: Invalid value 'sometimes' for argument '--color', expected one of 'always', 'auto', 'never'.
+    /// ```
+    pub fn possible_values(mut self, values: Vec<String>) -> Self {
+        self.choices = values;
+        self
     }
 
-    /// Add a `Template` that is a subcommand of an already existing template to the parser. You
-    /// need to provide a `Template` ID of the `Template` this `Template` will be the subcommand of.
+    /// Compare the values with the possible values, set by `possible_values()`, ignoring ASCII
+    /// case.
+    pub fn ignore_case(mut self, ignore: bool) -> Self {
+        self.ignore_case = ignore;
+        self
+    }
+
+    /// Set an environment variable, which is used when the argument is not present in the
+    /// command line arguments.
     ///
-    /// This method creates the `Template` for you, but it takes away some of the options.
-    pub fn add_subcommand<S: AsRef<str>>(
-        &mut self,
-        subargument_of: usize,
-        matches: S,
-        num_values: usize,
-        help_message: S,
-    ) -> TemplateId {
-        let id = self.generate_id();
-        let mut template = Template::new()
-            .matches(matches.as_ref())
-            .number_of_values(num_values)
-            .with_help(help_message.as_ref());
-        template.set_id(id);
-        template.subarg(subargument_of);
+    /// Values supplied in the command line arguments always take precedence, followed by the
+    /// configuration file, see `Parser::with_config_file()`. For templates taking
+    /// multiple values, the variable is split on whitespace. A variable which is set satisfies
+    /// `required()`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--token")
+    ///                         .number_of_values(1)
+    ///                         .env("MYPROG_TOKEN"));
+    ///
+    /// // $ MYPROG_TOKEN=secret myprog
+    /// // is the same as
+    /// // $ myprog --token secret
+    /// ```
+    pub fn env<S: AsRef<str>>(mut self, var: S) -> Self {
+        self.env = Some(var.as_ref().to_string());
+        self
+    }
 
-        self.add_to_map(template)
+    /// Allow values of this template to be negative numbers, such as `-5` or `-0.5`, even if
+    /// they look like a bundle of short flags.
+    ///
+    /// A negative number which is itself registered as a template is still treated as that
+    /// template.
+    ///
+    /// ```ignore
+    /// parser.add("-1", 0, "Do it once.");
+    /// parser.add("-2", 0, "Do it twice.");
+    /// parser.add_template(Template::new()
+    ///                         .matches("--add")
+    ///                         .number_of_values(2)
+    ///                         .allow_negative_numbers(true));
+    ///
+    /// // $ myprog --add -12 5
+    /// // '-12' is a value of '--add', not the flags '-1' and '-2'.
+    /// ```
+    pub fn allow_negative_numbers(mut self, allow: bool) -> Self {
+        self.negative_numbers = allow;
+        self
     }
 
-    /// Add a `Template` that is a subcommand of an already existing template to the parser. You
-    /// need to provide a `Template` ID of the `Template` this `Template` will be the subcommand of.
-    pub fn add_subcommand_template(
-        &mut self,
-        subargument_of: usize,
-        mut template: Template,
-    ) -> TemplateId {
-        let id = self.generate_id();
-        template.set_id(id);
-        template.subarg(subargument_of);
+    /// Allow values of this template to start with a dash, such as `-hello`.
+    ///
+    /// The following arguments are taken as values, up to the number of values, **even if they
+    /// are registered arguments**, only a `--` ends the values. Without this, a registered
+    /// argument always wins and ends the values, but `--message=-hello` can still be used to pass
+    /// such a value.
+    ///
+    /// ```ignore
+    /// parser.add("-v", 0, "Verbose.");
+    /// parser.add_template(Template::new()
+    ///                         .matches("--message")
+    ///                         .number_of_values(1)
+    ///                         .accept_dash_values(true));
+    ///
+    /// // $ myprog --message -v
+    /// // '-v' is a value of '--message', it's not parsed as a flag.
+    /// ```
+    pub fn accept_dash_values(mut self, accept: bool) -> Self {
+        self.dash_values = accept;
+        self
+    }
 
-        self.add_to_map(template)
+    /// Make this template match in any context, not only in the context of its parent command.
+    ///
+    /// Top-level templates already match in any context, so this is useful for subcommands.
+    /// A template matching in the current context takes precedence, followed by the top-level
+    /// templates and the global templates last.
+    ///
+    /// ```ignore
+    /// let db = parser.add("db", 0, "Database commands.");
+    /// let migrate = parser.add_subcommand(db, "migrate", 0, "Run the migrations.")?;
+    /// parser.add_subcommand_template(db, Template::new().matches("--dry-run").global(true))?;
+    ///
+    /// // $ myprog db migrate --dry-run
+    /// // '--dry-run' matches, even though the current context is 'migrate'.
+    /// ```
+    pub fn global(mut self, global: bool) -> Self {
+        self.global = global;
+        self
+    }
+
+    /// Set a help string for this template.
+    ///
+    /// ```ignore
+    /// parser.with_description("My awesome program!")
+    /// parser.add_template(Template::new()
+    ///                         .matches("--say")
+    ///                         .number_of_values(1)
+    ///                         .optional_values(true)
+    ///                         .with_help("Print a given argument."));
+    ///
+    /// // ...
+    /// parser.parse()?;
+    ///
+    ///
+    /// // $ myprog --say hello
+    /// // hello
+    /// // $ myprog --help
+    /// // myprog: My awesome program!
+    /// // usage:
+    /// //     $ myprog -[-arguments] [value/s]
+    /// // arguments:
+    /// //     --say [1 optional value\s]        Print a given argument.
+    /// //     --help                          Print this help message.
+    /// ```
+    pub fn with_help<S: AsRef<str>>(mut self, help_string: S) -> Self {
+        self.help = help_string.as_ref().into();
+        self
+    }
+
+    /// Collect values of this template until an exact match of a top level argument, instead of
+    /// any known argument.
+    ///
+    /// Subcommand names and unknown arguments starting with a dash are kept as values. The number
+    /// of values is still limited by `number_of_values()`, so this is mostly useful together with
+    /// `variadic()`. A `--` always ends the values.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--files").variadic(true).greedy(true));
+    /// parser.add("-v", 0, "Verbose.");
+    ///
+    /// // $ myprog --files a -b c -v
+    /// // '--files' has the values 'a', '-b' and 'c'.
+    /// ```
+    pub fn greedy(mut self, greedy: bool) -> Self {
+        self.greedy = greedy;
+        self
+    }
+
+    /// Split every value of this template on the given delimiter, so that `--tags a,b,c` has the
+    /// values `a`, `b` and `c`. An escaped delimiter, such as `a\,b`, is kept in the value.
+    ///
+    /// Every part counts as a value, towards `number_of_values()` and the likes, so
+    /// `number_of_values(2)` accepts both `--pair a,b` and `--pair a b`, while `--pair a,b,c` is
+    /// an error. The values from environment variables and configuration files are split too.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--tags")
+    ///                         .variadic(true)
+    ///                         .value_delimiter(','));
+    ///
+    /// // $ myprog --tags a,b c
+    /// // '--tags' has the values 'a', 'b' and 'c'.
+    /// ```
+    pub fn value_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Reject more values than this template takes, instead of leaving the extra values as
+    /// positional arguments.
+    ///
+    /// Parsing fails with `HpError::TooManyValues`, if an argument not starting with a dash,
+    /// which is not a known argument, follows the values.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--size")
+    ///                         .number_of_values(2)
+    ///                         .exact_values(true));
+    ///
+    /// // $ myprog --size 10 20 30
+    /// // ERROR: In argument '--size' at position 0, expected '2' value/s, but '3' follow.
+    /// ```
+    pub fn exact_values(mut self, exact: bool) -> Self {
+        self.exact_values = exact;
+        self
+    }
+
+    /// End the values of this template at any of the given arguments, even if they are not
+    /// registered arguments. The stopping argument is not a value, it's parsed as usual.
+    ///
+    /// This applies to `greedy()` templates and to `accept_dash_values()` as well.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("repeat")
+    ///                         .variadic(true)
+    ///                         .stop_at(vec!["then".into()]));
+    ///
+    /// // $ myprog repeat a b then c
+    /// // 'repeat' has the values 'a' and 'b', 'then' and 'c' are positional arguments.
+    /// ```
+    pub fn stop_at(mut self, stop: Vec<String>) -> Self {
+        self.stop_at = stop;
+        self
+    }
+
+    /// Make this template take a single `KEY=VALUE` pair, which is collected across all of its
+    /// occurrences, see `ParsedArguments::get_map()`.
+    ///
+    /// This supersedes `number_of_values()`, `variadic()` and `values_range()`. A value without
+    /// an `=` results in `HpError::MalformedKeyValue`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("-D").matches("--define").key_value(true));
+    ///
+    /// // $ myprog -D name=hp --define version=1.0
+    /// let defines = result.get_map("-D");
+    /// assert_eq!(defines["name"], "hp");
+    /// ```
+    pub fn key_value(mut self, key_value: bool) -> Self {
+        self.key_value = key_value;
+        self
+    }
+
+    /// Make this template take all of the following arguments as its values, as they are, which
+    /// ends the parsing like `--` does.
+    ///
+    /// This supersedes all the other options for the number of values, not even `--` is
+    /// interpreted after this template. Only one template of a parser can be set to take the
+    /// rest of the arguments, see `Parser::validate()`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--exec").rest(true));
+    ///
+    /// // $ myprog --exec ls -la --color
+    /// // '--exec' has the values 'ls', '-la' and '--color'.
+    /// ```
+    pub fn rest(mut self, rest: bool) -> Self {
+        self.rest = rest;
+        self
+    }
+
+    /// Name the value of this template, the name is shown in the help message instead of the
+    /// number of values.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--output")
+    ///                         .number_of_values(1)
+    ///                         .value_name("FILE"));
+    ///
+    /// // Arguments:
+    /// //     --output <FILE>        ...
+    /// ```
+    pub fn value_name<S: Into<String>>(self, name: S) -> Self {
+        self.value_names(vec![name.into()])
+    }
+
+    /// Name each of the values of this template, see `value_name()`.
+    ///
+    /// When there are fewer names than values, the last name is repeated. For a variable number
+    /// of values, the last name is followed by `...`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///                         .matches("--copy")
+    ///                         .number_of_values(2)
+    ///                         .value_names(vec!["SRC".into(), "DEST".into()]));
+    ///
+    /// // Arguments:
+    /// //     --copy <SRC> <DEST>        ...
+    /// ```
+    pub fn value_names(mut self, names: Vec<String>) -> Self {
+        self.value_names = names;
+        self
+    }
+
+    /// Put this template into a named section of the help message.
+    ///
+    /// Sections are shown in the order in which they were first used, after the templates without
+    /// a group. Subcommands are always shown with their parent, so this only has an effect on top
+    /// level templates.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--input").number_of_values(1).group("Input options"));
+    ///
+    /// // $ myprog --help
+    /// // ...
+    /// // Input options:
+    /// //     --input [1 value/s]        Read from a file.
+    /// ```
+    pub fn group<S: Into<String>>(mut self, group: S) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Hide this template, and its subcommands, from the help message and the man page.
+    ///
+    /// Hidden templates are parsed as usual, which is useful for deprecated aliases or internal
+    /// debugging flags.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--old-name").hidden(true));
+    /// ```
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Also accept `--no-<name>` for every long alias `--<name>` of this flag, which negates it.
+    ///
+    /// Only templates which take no values can be negated. Use `ParsedArguments::flag()` to get
+    /// the state of the flag, `ParsedArguments::has()` is `true` for a negated flag too.
+    ///
+    /// A negation doesn't run the action or the fold of the template, doesn't warn about a
+    /// deprecated template and isn't counted by `ParsedArguments::count()`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--color").negatable(true));
+    ///
+    /// // $ myprog --color --no-color
+    /// // The flag is negated, the last occurrence wins.
+    /// ```
+    pub fn negatable(mut self, negatable: bool) -> Self {
+        self.negatable = negatable;
+        self
     }
 
-    fn create_help(&self) -> String {
-        let mut result_string = String::new();
+    /// Mark this template as deprecated, with the argument which replaces it.
+    ///
+    /// A deprecated template is still parsed as usual, but a warning is printed to stderr every
+    /// time it's matched, see `Parser::on_deprecation()` to change that. An empty replacement
+    /// leaves out the hint.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new().matches("--old-flag").deprecated("--new-flag"));
+    ///
+    /// // $ myprog --old-flag
+    /// // warning: '--old-flag' is deprecated, use '--new-flag'
+    /// ```
+    pub fn deprecated<S: Into<String>>(mut self, replacement: S) -> Self {
+        self.deprecated = Some(replacement.into());
+        self
+    }
+
+    /// Set an action that will be executed immediately when a command is parsed.
+    ///
+    /// This action is a function with the following signature: `fn(Vec<String>) -> ()`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    ///
+    /// parser.add_subcommand_template(id, Template::new()
+    ///    .matches("--add")
+    ///    .number_of_values(2)
+    ///    .on_parse(|values| {
+    ///        let (a, b): (i32, i32) = (values[0].parse().unwrap(), values[1].parse().unwrap());
+    ///        println!("{}", a + b);
+    ///    }))?;
+    ///
+    /// ```
+    pub fn on_parse<F: FnMut(Vec<String>) + MaybeSend + 'static>(mut self, mut action: F) -> Self {
+        self.action = Action::new(move |_, _, values| {
+            action(values);
+            Ok(())
+        });
+        self
+    }
+
+    /// Set an action, which can reject the parsed values by returning an error message.
+    ///
+    /// This action is a function with the following signature: `fn(Vec<String>) -> Result<(), String>`.
+    /// Returning `Err` aborts parsing with `HpError::ActionFailed`.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///    .matches("--port")
+    ///    .number_of_values(1)
+    ///    .on_parse_try(|values| match values[0].parse::<u16>() {
+    ///        Ok(port) if port >= 1024 => Ok(()),
+    ///        _ => Err("expected a port between 1024 and 65535".into()),
+    ///    }));
+    /// ```
+    pub fn on_parse_try<F: FnMut(Vec<String>) -> Result<(), String> + MaybeSend + 'static>(
+        mut self,
+        mut action: F,
+    ) -> Self {
+        self.action = Action::new(move |_, _, values| action(values));
+        self
+    }
+
+    /// Set an action, which also receives the matched alias and the ID of this template.
+    ///
+    /// This action is a function with the following signature: `fn(&str, TemplateId, Vec<String>) -> ()`.
+    /// It's useful when one closure is shared by more templates, or by more aliases of a single
+    /// template.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///    .matches("-v")
+    ///    .matches("--verbose")
+    ///    .on_parse_ctx(|name, _id, _values| println!("Verbose mode set by '{name}'.")));
+    /// ```
+    pub fn on_parse_ctx<F: FnMut(&str, TemplateId, Vec<String>) + MaybeSend + 'static>(
+        mut self,
+        mut action: F,
+    ) -> Self {
+        self.action = Action::new(move |name, id, values| {
+            action(name, id, values);
+            Ok(())
+        });
+        self
+    }
+
+    /// Reduce the values of every occurrence of this template to a single value, which is
+    /// returned by `ParsedArguments::folded()`.
+    ///
+    /// The function receives the value folded so far, `initial` for the first occurrence, and
+    /// the values of the occurrence. A fold is independent of the action of the template.
+    ///
+    /// ```ignore
+    /// let sum = parser.add_template(Template::new()
+    ///    .matches("--add")
+    ///    .number_of_values(1)
+    ///    .fold(0, |sum, values| sum + values[0].parse::<i32>().unwrap_or(0)));
+    /// let result = parser.parse(None)?;
+    ///
+    /// // $ myprog --add 1 --add 2
+    /// assert_eq!(result.folded::<i32>(sum), Some(3));
+    /// ```
+    pub fn fold<T, F>(mut self, initial: T, f: F) -> Self
+    where
+        T: Clone + MaybeSend + 'static,
+        F: Fn(T, Vec<String>) -> T + MaybeSend + 'static,
+    {
+        self.fold = Fold::new(initial, f);
+        self
+    }
+
+    /// Validate each parsed value of this template, including the values taken from a fallback,
+    /// but not the default values. The first rejected value aborts parsing with
+    /// `HpError::ValueValidationFailed`, before the action of the template runs.
+    ///
+    /// ```ignore
+    /// parser.add_template(Template::new()
+    ///    .matches("--host")
+    ///    .variadic(true)
+    ///    .validate_each(|value| match value.parse::<std::net::IpAddr>() {
+    ///        Ok(_) => Ok(()),
+    ///        Err(_) => Err("expected an IP address".into()),
+    ///    }));
+    /// ```
+    pub fn validate_each<F: Fn(&str) -> Result<(), String> + MaybeSend + 'static>(
+        mut self,
+        f: F,
+    ) -> Self {
+        self.validator = Validator::new(f);
+        self
+    }
+
+    /// Add `value` to `values`, split on the delimiter of this template, if it has one.
+    fn push_value(&self, values: &mut Vec<String>, value: &str) {
+        let Some(delimiter) = self.delimiter else {
+            values.push(value.to_string());
+            return;
+        };
+
+        let mut part = String::new();
+        let mut chars = value.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' && chars.peek() == Some(&delimiter) {
+                part.push(delimiter);
+                chars.next();
+            } else if c == delimiter {
+                values.push(std::mem::take(&mut part));
+            } else {
+                part.push(c);
+            }
+        }
+        values.push(part);
+    }
+
+    /// Run the action of this template, if it has one.
+    fn run_action(&self, name: &str, values: &[String]) -> Result<(), HpError> {
+        self.action
+            .call(name, self.id, values.to_vec())
+            .map_err(|message| HpError::ActionFailed(name.to_string(), message))
+    }
+
+    /// Create the label of this template, as shown in the help message.
+    fn help_label(&self) -> String {
+        let mut label = self.matches.join(" | ");
+        if self.negatable {
+            for name in self.matches.iter().filter_map(|m| m.strip_prefix("--")) {
+                write!(label, " | --no-{name}").unwrap_or(());
+            }
+        }
+        if let Some(values) = self.values_label() {
+            write!(label, " {values}").unwrap_or(());
+        }
+        if !self.choices.is_empty() {
+            write!(label, " {{{}}}", self.choices.join("|")).unwrap_or(());
+        }
+
+        label
+    }
+
+    /// Describe the values this template takes, if it takes any.
+    fn values_label(&self) -> Option<String> {
+        let optional = match self.optional_vals {
+            true => "optional ",
+            false => "",
+        };
+        if let Some(last) = self.value_names.last() {
+            let (min, max) = self.value_bounds();
+            let mut names: Vec<String> =
+                self.value_names.iter().map(|n| format!("<{n}>")).collect();
+            if min == max {
+                names.resize(max, format!("<{last}>"));
+            } else {
+                names.last_mut().unwrap().push_str("...");
+            }
+            if names.is_empty() {
+                None
+            } else if self.optional_vals {
+                Some(format!("[{}]", names.join(" ")))
+            } else {
+                Some(names.join(" "))
+            }
+        } else if self.rest {
+            Some("[arguments...]".to_string())
+        } else if self.key_value {
+            Some("[KEY=VALUE]".to_string())
+        } else if let Some((min, max)) = self.range {
+            Some(format!("[{min}-{max} {optional}value/s]"))
+        } else if let Some(n) = self.at_least {
+            Some(format!("[{n} or more {optional}value/s]"))
+        } else if self.variadic {
+            Some(format!("[{optional}value/s...]"))
+        } else if self.num_values > 0 {
+            Some(format!("[{} {optional}value/s]", self.num_values))
+        } else {
+            None
+        }
+    }
+
+    /// Return the minimum and the maximum number of values this template takes.
+    fn value_bounds(&self) -> (usize, usize) {
+        if self.rest {
+            (0, usize::MAX)
+        } else if self.key_value {
+            (1, 1)
+        } else if let Some(range) = self.range {
+            range
+        } else if let Some(n) = self.at_least {
+            (n, usize::MAX)
+        } else if self.variadic {
+            (1, usize::MAX)
+        } else {
+            (self.num_values, self.num_values)
+        }
+    }
+
+    /// Check whether all the `values` are `KEY=VALUE` pairs, if this is a key-value template.
+    fn check_key_values(&self, values: &[String]) -> Result<(), HpError> {
+        match values
+            .iter()
+            .find(|value| self.key_value && !value.contains('='))
+        {
+            Some(value) => Err(HpError::MalformedKeyValue(value.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Check whether this template takes `count` values, `position` is the position of the
+    /// argument in the command line.
+    fn check_number_of_values(
+        &self,
+        name: &str,
+        count: usize,
+        position: usize,
+    ) -> Result<(), HpError> {
+        let (min, max) = self.value_bounds();
+        if count <= max && (self.optional_vals || count >= min) {
+            return Ok(());
+        }
+
+        Err(match self.range {
+            Some((min, max)) => {
+                HpError::NumberOfValuesRange(name.to_string(), count, min, max, position)
+            }
+            None => HpError::NumberOfValues(name.to_string(), count, min, position),
+        })
+    }
+
+    /// Check all the `values` with the validator of this template, if it has one.
+    fn check_each(&self, name: &str, values: &[String]) -> Result<(), HpError> {
+        for value in values.iter() {
+            if let Err(message) = self.validator.call(value) {
+                return Err(HpError::ValueValidationFailed(
+                    name.to_string(),
+                    value.to_string(),
+                    message,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check whether all the `values` are possible values of this template.
+    fn check_choices(&self, name: &str, values: &[String]) -> Result<(), HpError> {
+        if self.choices.is_empty() {
+            return Ok(());
+        }
+
+        for value in values.iter() {
+            if !self.choices.iter().any(|choice| match self.ignore_case {
+                true => choice.eq_ignore_ascii_case(value),
+                false => choice == value,
+            }) {
+                return Err(HpError::InvalidChoice(
+                    name.to_string(),
+                    value.to_string(),
+                    self.choices.clone(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn set_id(&mut self, id: usize) {
+        self.id = id
+    }
+
+    pub(crate) fn subarg(&mut self, id: usize) {
+        let _ = self.subargument_of.insert(id);
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A read-only description of a `Template` added to a `Parser`, see `Parser::templates()`.
+pub struct TemplateInfo {
+    /// The values, which identify the template in the command line arguments.
+    pub matches: Vec<String>,
+    /// The number of values the template takes.
+    pub num_values: usize,
+    /// Whether the values of the template are optional.
+    pub optional_vals: bool,
+    /// The help string of the template.
+    pub help: String,
+    /// The ID of the parent template, if the template is a subcommand.
+    pub subargument_of: Option<TemplateId>,
+    /// The unique identifier of the template.
+    pub id: TemplateId,
+}
+
+impl From<&Template> for TemplateInfo {
+    fn from(template: &Template) -> Self {
+        Self {
+            matches: template.matches.clone(),
+            num_values: template.num_values,
+            optional_vals: template.optional_vals,
+            help: template.help.clone(),
+            subargument_of: template.subargument_of,
+            id: template.id,
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+/// Command line argument parser.
+///
+/// ```ignore
+/// let parser = Parser::new()
+///                 .exit_on_help(false)
+///                 .with_description("My amazing program!")
+///                 .with_usage("$ myprog [commmand] --[subcommand/s] [value/s]")
+///                 .with_author("[REDACTED]");
+/// ```
+pub struct Parser {
+    stored: HashMap<Key, Template>,
+    order: Vec<TemplateId>,
+    duplicates: Vec<String>,
+    invalid_names: Vec<String>,
+    exclusive_groups: Vec<Vec<TemplateId>>,
+    required_groups: Vec<Vec<TemplateId>>,
+    commands: Vec<TemplateId>,
+    last_id: usize,
+    exit_on_help: bool,
+    exit_on_version: bool,
+    auto_help: bool,
+    contextual_help: bool,
+    help_flags: Vec<String>,
+    version_flags: Vec<String>,
+    strict: bool,
+    capture_unknown: bool,
+    response_files: bool,
+    abbreviations: bool,
+    prompts: bool,
+    explain: bool,
+    normalize_dashes: bool,
+    help_width: Option<usize>,
+    sort_help: SortMode,
+    author: String,
+    description: String,
+    usage: String,
+    show_usage: bool,
+    program_name: String,
+    version: Option<String>,
+    help: Option<String>,
+    footer: String,
+    examples: Vec<(String, String)>,
+    error_exit_code: i32,
+    config_file: Option<PathBuf>,
+    on_deprecation: Action,
+    on_explain: Action,
+}
+
+impl Parser {
+    /// Create a new `Parser` with default configuration, no author and description.
+    pub fn new() -> Self {
+        let exe_name = match current_exe() {
+            Ok(pb) => {
+                if let Some(name) = pb.file_name() {
+                    name.to_str().unwrap_or("").to_string()
+                } else {
+                    "".to_string()
+                }
+            }
+            Err(_) => "".to_string(),
+        };
+        Self {
+            stored: HashMap::new(),
+            order: Vec::new(),
+            duplicates: Vec::new(),
+            invalid_names: Vec::new(),
+            exclusive_groups: Vec::new(),
+            required_groups: Vec::new(),
+            commands: Vec::new(),
+            last_id: 0,
+            exit_on_help: true,
+            exit_on_version: true,
+            auto_help: true,
+            contextual_help: false,
+            help_flags: vec!["-h".into(), "--help".into()],
+            version_flags: vec!["-V".into(), "--version".into()],
+            strict: false,
+            capture_unknown: false,
+            response_files: false,
+            abbreviations: false,
+            prompts: false,
+            explain: false,
+            normalize_dashes: false,
+            help_width: None,
+            sort_help: SortMode::Insertion,
+            author: "".to_string(),
+            description: "".to_string(),
+            usage: "".to_string(),
+            show_usage: true,
+            program_name: exe_name,
+            version: None,
+            help: None,
+            footer: "".to_string(),
+            examples: Vec::new(),
+            error_exit_code: 2,
+            config_file: None,
+            on_deprecation: Action::default(),
+            on_explain: Action::default(),
+        }
+    }
+
+    /// Specifies, whether the program should exit after printing the help message when the
+    /// '--help' or '-h' command line arguments are specified.
+    ///
+    /// If set to `false`, nothing is printed and `parse()` returns `HpError::HelpRequested`
+    /// containing the help message instead, so the caller can decide what to do with it.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().exit_on_help(false);
+    ///
+    /// match parser.parse(None) {
+    ///     Err(HpError::HelpRequested(help)) => println!("{help}"),
+    ///     Err(err) => return Err(err),
+    ///     Ok(result) => (),
+    /// }
+    /// ```
+    pub fn exit_on_help(mut self, v: bool) -> Self {
+        self.exit_on_help = v;
+        self
+    }
+
+    /// Specifies, whether the program should exit after printing the version when the
+    /// '--version' or '-V' command line arguments are specified.
+    ///
+    /// If set to `false`, nothing is printed and `parse()` returns `HpError::VersionRequested`
+    /// containing the version string instead.
+    pub fn exit_on_version(mut self, v: bool) -> Self {
+        self.exit_on_version = v;
+        self
+    }
+
+    /// Specifies, whether the help message should be printed automatically, when one of the help
+    /// flags is present in the command line arguments.
+    ///
+    /// When disabled, the help flags can be used by other templates.
+    pub fn auto_help(mut self, v: bool) -> Self {
+        self.auto_help = v;
+        self
+    }
+
+    /// Specifies the code the program exits with, when `parse_or_exit()` fails, 2 by default.
+    pub fn error_exit_code(mut self, code: i32) -> Self {
+        self.error_exit_code = code;
+        self
+    }
+
+    /// Specifies, whether the help flags after a command only describe the subcommands of the
+    /// command, such as `myprog remote --help`. Off by default.
+    ///
+    /// The nearest command with subcommands is described, so a help flag following a subcommand
+    /// without subcommands describes its parent. Without any such command, or with a help
+    /// message set by `set_help()`, the whole help message is used.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().contextual_help(true);
+    /// let remote = parser.add("remote", 0, "Manage the remotes.");
+    /// parser.add_subcommand(remote, "add", 2, "Add a remote.")?;
+    ///
+    /// // $ myprog remote --help
+    /// // myprog remote: Manage the remotes.
+    /// // Arguments:
+    /// //     add [2 value/s]        Add a remote.
+    /// //     -h, --help             Print this help message!
+    /// ```
+    pub fn contextual_help(mut self, v: bool) -> Self {
+        self.contextual_help = v;
+        self
+    }
+
+    /// Specifies the command line arguments, which trigger the help message, '-h' and '--help'
+    /// by default.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().help_flags(vec!["--help".into()]);
+    /// parser.add("-h", 1, "Host to connect to.");
+    /// ```
+    pub fn help_flags(mut self, v: Vec<String>) -> Self {
+        self.help_flags = v;
+        self
+    }
+
+    /// Specifies the command line arguments, which print the version, '-V' and '--version' by
+    /// default.
+    pub fn version_flags(mut self, v: Vec<String>) -> Self {
+        self.version_flags = v;
+        self
+    }
+
+    /// Specifies, whether parsing should fail on arguments starting with a dash, which do not match
+    /// any template and are not values of another argument.
+    ///
+    /// Bare words, which do not start with a dash, are never reported.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().strict(true);
+    /// parser.add("--verbose", 0, "Print more information.");
+    ///
+    /// // $ myprog --vebose
+    /// // ERROR: Unknown argument '--vebose', did you mean '--verbose'?
+    /// ```
+    pub fn strict(mut self, v: bool) -> Self {
+        self.strict = v;
+        self
+    }
+
+    /// Specifies, whether arguments starting with a dash, which do not match any template and
+    /// are not values of another argument, are collected into `ParsedArguments::unknown()`,
+    /// instead of being ignored. This takes precedence over `strict()`.
+    ///
+    /// The values of unknown arguments can't be told apart from positional arguments, unless
+    /// they are attached with `=`.
+    pub fn capture_unknown(mut self, v: bool) -> Self {
+        self.capture_unknown = v;
+        self
+    }
+
+    /// Specifies, whether arguments starting with `@` should be replaced with the contents of the
+    /// file they name, split on whitespace.
+    ///
+    /// Response files can not contain other response files. Arguments after `--` are never
+    /// replaced.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().response_files(true);
+    ///
+    /// // $ echo "--say hello" > args.txt
+    /// // $ myprog @args.txt
+    /// // is the same as
+    /// // $ myprog --say hello
+    /// ```
+    pub fn response_files(mut self, v: bool) -> Self {
+        self.response_files = v;
+        self
+    }
+
+    /// Specifies, whether long arguments can be abbreviated, as long as the abbreviation is a
+    /// prefix of exactly one long argument available in the current context.
+    ///
+    /// An exact match always takes precedence over an abbreviation.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().allow_abbreviations(true);
+    /// parser.add("--verbose", 0, "Print more information.");
+    /// parser.add("--version", 0, "Print the version.");
+    ///
+    /// // $ myprog --verb
+    /// // is the same as
+    /// // $ myprog --verbose
+    /// //
+    /// // $ myprog --ver
+    /// // ERROR: Ambiguous argument '--ver', it could be any of '--verbose', '--version'.
+    /// ```
+    pub fn allow_abbreviations(mut self, v: bool) -> Self {
+        self.abbreviations = v;
+        self
+    }
+
+    /// Specifies, whether the parser explains its decisions, by printing a line to stderr for
+    /// every argument: the key it matched and in which context, how many values it took and why
+    /// the values ended. Off by default.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().explain(true);
+    /// parser.add("--say", 1, "Say something.");
+    ///
+    /// // $ myprog --say hi there
+    /// // explain: '--say' matches the key (0, '--say') in the current context 0
+    /// // explain: '--say' takes 1 value/s, the values end because the maximum is 1
+    /// // explain: 'there' is not an argument in context 1, it's a positional argument
+    /// ```
+    pub fn explain(mut self, v: bool) -> Self {
+        self.explain = v;
+        self
+    }
+
+    /// Explain the decisions of the parser to a function instead of stderr, see `explain()`,
+    /// which this method also enables. The function receives the explanations without the
+    /// `explain: ` prefix.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().on_explain(|line| log::debug!("{line}"));
+    /// ```
+    pub fn on_explain<F: FnMut(&str) + MaybeSend + 'static>(mut self, mut f: F) -> Self {
+        self.explain = true;
+        self.on_explain = Action::new(move |line, _, _| {
+            f(line);
+            Ok(())
+        });
+        self
+    }
+
+    /// Specifies, whether missing required templates with a prompt ask for their values, see
+    /// `Template::prompt()`. Off by default.
+    ///
+    /// The values read from stdin take the lowest precedence, after the configuration file and
+    /// the environment variables.
+    pub fn allow_prompts(mut self, v: bool) -> Self {
+        self.prompts = v;
+        self
+    }
+
+    /// Specifies, whether the leading dashes of arguments are ignored, so that `verbose`,
+    /// `-verbose` and `--verbose` are all the same argument.
+    ///
+    /// This applies both to the parsed command line arguments and to the names passed to the
+    /// getters of `ParsedArguments`, such as `has()`. A single `-` or `--` is never changed. Off
+    /// by default.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().normalize_dashes(true);
+    /// parser.add("--verbose", 0, "Print more information.");
+    /// let result = parser.parse(None)?;
+    ///
+    /// // $ myprog verbose
+    /// assert!(result.has("verbose") && result.has("--verbose"));
+    /// ```
+    pub fn normalize_dashes(mut self, v: bool) -> Self {
+        self.normalize_dashes = v;
+
+        // Store the templates again in the order they were added, so that the same aliases
+        // collide, as if the templates were added after this call.
+        let mut stored: Vec<(usize, Template)> = std::mem::take(&mut self.stored)
+            .into_iter()
+            .map(|((context, _), template)| (context, template))
+            .collect();
+        stored.sort_by_key(|(context, template)| (template.id, *context));
+        stored.dedup_by_key(|(context, template)| (template.id, *context));
+        for (context, template) in stored {
+            for name in template.matches.iter() {
+                if name.is_empty() || name.chars().any(char::is_whitespace) {
+                    continue;
+                }
+                let key = (context, self.key_name(name).to_string());
+                if self.stored.insert(key, template.clone()).is_some() {
+                    self.duplicates.push(name.clone());
+                }
+            }
+        }
+        self
+    }
+
+    /// Set a function, which is called instead of printing the warning, when a deprecated
+    /// template is matched.
+    ///
+    /// The function receives the matched alias and the replacement given to
+    /// `Template::deprecated()`. Pass a function doing nothing to silence the warnings.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().on_deprecation(|name, replacement| {
+    ///     log::warn!("'{name}' will be removed in the next release, use '{replacement}'");
+    /// });
+    /// ```
+    pub fn on_deprecation<F: FnMut(&str, &str) + MaybeSend + 'static>(mut self, mut f: F) -> Self {
+        self.on_deprecation = Action::new(move |name, _, values| {
+            f(name, &values[0]);
+            Ok(())
+        });
+        self
+    }
+
+    /// Specifies the width of the help message, help strings of templates which don't fit are
+    /// wrapped and aligned under the first line.
+    ///
+    /// If this is not set, the width of the terminal is used, falling back to 80, when the output
+    /// is not a terminal.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().help_width(100);
+    /// ```
+    pub fn help_width(mut self, width: usize) -> Self {
+        self.help_width = Some(width);
+        self
+    }
+
+    /// Specifies the order of the arguments in the help message, `SortMode::Insertion` by
+    /// default.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().sort_help(SortMode::Alphabetical);
+    /// ```
+    pub fn sort_help(mut self, mode: SortMode) -> Self {
+        self.sort_help = mode;
+        self
+    }
+
+    /// Specifies a configuration file of `key = value` lines, whose values are used for the
+    /// arguments not present in the command line arguments.
+    ///
+    /// A key is an alias of a template without the leading dashes. The values are split on
+    /// whitespace for templates taking multiple values, flags are set unless the value is
+    /// `false`. Empty lines and lines starting with `#` or `;` are ignored, a value can be enclosed
+    /// in double quotes. The command line arguments take precedence over the file, which takes
+    /// precedence over the environment variables, see `Template::env()`.
+    ///
+    /// The file is read by `parse()`, a missing file is ignored and a malformed line results in
+    /// `HpError::ConfigParseError`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().with_config_file("/etc/myprog.conf");
+    /// parser.add("--color", 1, "When to use colors.");
+    ///
+    /// // $ cat /etc/myprog.conf
+    /// // color = never
+    /// // $ myprog
+    /// // is the same as
+    /// // $ myprog --color never
+    /// ```
+    pub fn with_config_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Specifies the author of the program, will be used when printing the help message.
+    pub fn with_author<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.author = v.as_ref().to_string();
+        self
+    }
+
+    /// Specifies the description of the program, will be used when printing the help message.
+    pub fn with_description<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.description = v.as_ref().to_string();
+        self
+    }
+
+    /// Specifies a text, which is printed at the end of the help message, after the arguments and
+    /// the examples.
+    pub fn with_footer<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.footer = v.as_ref().to_string();
+        self
+    }
+
+    /// Add an example invocation of the program with its description, which is shown in the
+    /// `Examples:` section of the help message.
+    ///
+    /// ```ignore
+    /// parser.add_example("myprog --say hi", "Print 'hi'.");
+    ///
+    /// // $ myprog --help
+    /// // ...
+    /// // Examples:
+    /// //     $ myprog --say hi
+    /// //         Print 'hi'.
+    /// ```
+    pub fn add_example<I: AsRef<str>, D: AsRef<str>>(&mut self, invocation: I, description: D) {
+        self.examples
+            .push((invocation.as_ref().into(), description.as_ref().into()));
+    }
+
+    /// Specifies the usage of the program, will be used when printing the help message.
+    ///
+    /// If nothing is specified, `hp` will try to interpret the given templates and create a custom
+    /// usage string.
+    ///
+    /// `{prog}` in the usage is replaced by the program name and `{--name}` by the top-level
+    /// template matching `--name`, followed by its values. Unknown placeholders are kept as is.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new()
+    ///     .with_usage("{prog} {--output} [files...]")
+    ///     .with_program_name("myprog");
+    /// parser.add_template(Template::new()
+    ///                         .matches("--output")
+    ///                         .number_of_values(1)
+    ///                         .value_name("FILE"));
+    ///
+    /// // Usage:
+    /// //     myprog --output <FILE> [files...]
+    /// ```
+    pub fn with_usage<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.usage = v.as_ref().to_string();
+        self
+    }
+
+    /// Leave the `Usage:` section out of the help message, neither the usage set with
+    /// `with_usage()`, nor the generated synopsis is shown.
+    pub fn no_usage(mut self) -> Self {
+        self.show_usage = false;
+        self
+    }
+
+    /// Specifies the program name, will be used when printing the help message.
+    ///
+    /// If none is specified the name of the binary will be be used.
+    pub fn with_program_name<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.program_name = v.as_ref().to_string();
+        self
+    }
+
+    /// Specifies the version of the program, which is printed when the '--version' or '-V'
+    /// command line arguments are specified.
+    ///
+    /// If no version is specified, '--version' and '-V' are treated as any other argument.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new()
+    ///                 .with_program_name("myprog")
+    ///                 .with_version("1.0.0");
+    ///
+    /// // $ myprog --version
+    /// // myprog 1.0.0
+    /// ```
+    pub fn with_version<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.version = Some(v.as_ref().to_string());
+        self
+    }
+
+    /// Set a completely custom help string, which will be used when printing the `--help`
+    /// string.
+    pub fn set_help<S: AsRef<str>>(mut self, v: S) -> Self {
+        self.help = Some(v.as_ref().to_string());
+        self
+    }
+
+    fn generate_id(&mut self) -> usize {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn add_to_map(&mut self, mut template: Template) -> TemplateId {
+        let template_id = self.generate_id();
+        template.set_id(template_id);
+        let matches = template.matches.clone();
+        for name in matches.iter() {
+            if name.is_empty() || name.chars().any(char::is_whitespace) {
+                self.invalid_names.push(name.clone());
+                continue;
+            }
+            let subarg = template.subargument_of.unwrap_or(0);
+            let key_name = self.key_name(name).to_string();
+            let new_name = (subarg, key_name.clone());
+            if self.stored.insert(new_name, template.clone()).is_some() {
+                self.duplicates.push(name.clone());
+            }
+            if template.global
+                && self
+                    .stored
+                    .insert((GLOBAL, key_name), template.clone())
+                    .is_some()
+            {
+                self.duplicates.push(name.clone());
+            }
+        }
+        self.order.push(template_id);
+
+        template_id
+    }
+
+    /// Add a new `Template` to the parser. Return the ID of the `Template`.
+    ///
+    /// This method is an abstraction, it creates the `Template` for you, but it takes away some of the options.
+    pub fn add<M: AsRef<str>, H: AsRef<str>>(
+        &mut self,
+        matches: M,
+        num_values: usize,
+        help_message: H,
+    ) -> TemplateId {
+        let template = Template::new()
+            .matches(matches)
+            .number_of_values(num_values)
+            .with_help(help_message);
+        self.add_to_map(template)
+    }
+
+    /// Add a new `Template` for each of the `(matches, num_values, help_message)` tuples, see
+    /// `add()`. Return the IDs of the `Template`s in the same order.
+    ///
+    /// ```ignore
+    /// let ids = parser.add_all(&[
+    ///     ("-v", 0, "Print more information."),
+    ///     ("-q", 0, "Print less information."),
+    ///     ("--output", 1, "Write to a file."),
+    /// ]);
+    /// ```
+    pub fn add_all(&mut self, specs: &[(&str, usize, &str)]) -> Vec<TemplateId> {
+        specs
+            .iter()
+            .map(|(matches, num_values, help_message)| self.add(matches, *num_values, help_message))
+            .collect()
+    }
+
+    /// A a new `Template` to the parser. Return the ID of the `Template`.
+    ///
+    /// Given a template, add it to the parser.
+    pub fn add_template(&mut self, template: Template) -> TemplateId {
+        self.add_to_map(template)
+    }
+
+    /// Add a `Template` that is a subcommand of an already existing template to the parser. You
+    /// need to provide a `Template` ID of the `Template` this `Template` will be the subcommand of.
+    ///
+    /// Every matched template becomes the context of the following arguments. An argument is
+    /// looked up among the subcommands of the context first, then among the subcommands of its
+    /// parent and so on, up to the top level arguments, the nearest match wins. This allows
+    /// switching to a sibling of any ancestor in a single command line:
+    ///
+    /// ```ignore
+    /// let db = parser.add("db", 0, "Manage the database.");
+    /// let migrate = parser.add_subcommand(db, "migrate", 0, "Run the migrations.")?;
+    /// parser.add_subcommand(migrate, "up", 0, "Apply the migrations.")?;
+    /// parser.add_subcommand(db, "seed", 0, "Fill the database.")?;
+    ///
+    /// // $ myprog db migrate up seed
+    /// // 'seed' is matched as a subcommand of 'db', after 'migrate up'.
+    /// ```
+    ///
+    /// This method creates the `Template` for you, but it takes away some of the options.
+    ///
+    /// Returns `HpError::UnknownParent`, if there is no template with the given ID.
+    pub fn add_subcommand<M: AsRef<str>, H: AsRef<str>>(
+        &mut self,
+        subargument_of: usize,
+        matches: M,
+        num_values: usize,
+        help_message: H,
+    ) -> Result<TemplateId, HpError> {
+        self.check_parent(subargument_of)?;
+        let id = self.generate_id();
+        let mut template = Template::new()
+            .matches(matches.as_ref())
+            .number_of_values(num_values)
+            .with_help(help_message.as_ref());
+        template.set_id(id);
+        template.subarg(subargument_of);
+
+        Ok(self.add_to_map(template))
+    }
+
+    /// Add a `Template` that is a subcommand of an already existing template to the parser. You
+    /// need to provide a `Template` ID of the `Template` this `Template` will be the subcommand of.
+    ///
+    /// Returns `HpError::UnknownParent`, if there is no template with the given ID.
+    pub fn add_subcommand_template(
+        &mut self,
+        subargument_of: usize,
+        mut template: Template,
+    ) -> Result<TemplateId, HpError> {
+        self.check_parent(subargument_of)?;
+        let id = self.generate_id();
+        template.set_id(id);
+        template.subarg(subargument_of);
+
+        Ok(self.add_to_map(template))
+    }
+
+    /// Add a subcommand of an already existing template for each of the
+    /// `(matches, num_values, help_message)` tuples, see `add_subcommand()`. Return the IDs of the
+    /// `Template`s in the same order.
+    ///
+    /// Returns `HpError::UnknownParent`, if there is no template with the given ID, in which
+    /// case nothing is added.
+    pub fn add_subcommands_all(
+        &mut self,
+        subargument_of: usize,
+        specs: &[(&str, usize, &str)],
+    ) -> Result<Vec<TemplateId>, HpError> {
+        self.check_parent(subargument_of)?;
+        specs
+            .iter()
+            .map(|(matches, num_values, help_message)| {
+                self.add_subcommand(subargument_of, matches, *num_values, help_message)
+            })
+            .collect()
+    }
+
+    /// Add a copy of the `Template` as a subcommand of each of the given parents, for options
+    /// shared by several subcommands. Return the IDs of the copies in the order of `parents`.
+    ///
+    /// Returns `HpError::UnknownParent`, if one of the parents doesn't exist, in which case
+    /// nothing is added.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// let migrate = parser.add("migrate", 0, "Run the migrations.");
+    /// let dump = parser.add("dump", 0, "Dump the database.");
+    /// parser.add_subcommand_to_many(
+    ///     &[migrate, dump],
+    ///     Template::new().matches("--db-url").number_of_values(1),
+    /// )?;
+    ///
+    /// // $ myprog dump --db-url postgres://localhost
+    /// ```
+    pub fn add_subcommand_to_many(
+        &mut self,
+        parents: &[TemplateId],
+        template: Template,
+    ) -> Result<Vec<TemplateId>, HpError> {
+        for parent in parents {
+            self.check_parent(*parent)?;
+        }
+        parents
+            .iter()
+            .map(|parent| self.add_subcommand_template(*parent, template.clone()))
+            .collect()
+    }
+
+    /// Return the ID of the **top-level** template, which has the given alias.
+    ///
+    /// ```ignore
+    /// let expand = parser.add_template(Template::new().matches("-x").matches("--expand"));
+    ///
+    /// assert_eq!(parser.template_id_of("--expand"), Some(expand));
+    /// ```
+    pub fn template_id_of(&self, alias: &str) -> Option<TemplateId> {
+        let key = (0, self.key_name(alias).to_string());
+        self.stored.get(&key).map(|t| t.id)
+    }
+
+    /// Check whether two aliases belong to the same **top-level** template.
+    pub fn same_template(&self, a: &str, b: &str) -> bool {
+        match (self.template_id_of(a), self.template_id_of(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Check whether a template with the given ID is stored in this parser, a template without
+    /// any valid alias is not.
+    fn check_parent(&self, id: TemplateId) -> Result<(), HpError> {
+        match self.stored.values().any(|t| t.id == id) {
+            true => Ok(()),
+            false => Err(HpError::UnknownParent(id)),
+        }
+    }
+
+    /// Make the templates with the given IDs mutually exclusive, at most one of them can be
+    /// present in the command line arguments.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// let json = parser.add("--json", 0, "Output JSON.");
+    /// let yaml = parser.add("--yaml", 0, "Output YAML.");
+    /// parser.exclusive_group(vec![json, yaml]);
+    ///
+    /// // $ myprog --json --yaml
+    /// // ERROR: Arguments '--json', '--yaml' can not be used together.
+    /// ```
+    pub fn exclusive_group(&mut self, ids: Vec<TemplateId>) {
+        self.exclusive_groups.push(ids);
+    }
+
+    /// Require at least one of the templates with the given IDs to be present in the command
+    /// line arguments.
+    ///
+    /// Together with `exclusive_group()`, exactly one of the templates has to be present.
+    pub fn required_group(&mut self, ids: Vec<TemplateId>) {
+        self.required_groups.push(ids);
+    }
+
+    /// Add all the templates of `other` to this parser, together with its exclusive and required
+    /// groups and commands. The settings of `other`, such as its help message, are ignored.
+    ///
+    /// The templates are given new IDs, so that they don't collide with the IDs of this parser,
+    /// the returned map translates the IDs of `other` to the new ones. Aliases of the top level
+    /// templates present in both of the parsers are reported by `validate()`.
+    ///
+    /// ```ignore
+    /// fn logging() -> (Parser, TemplateId) {
+    ///     let mut parser = Parser::new();
+    ///     let verbose = parser.add("--verbose", 0, "Print more information.");
+    ///     (parser, verbose)
+    /// }
+    ///
+    /// let mut parser = Parser::new();
+    /// let (logging, verbose) = logging();
+    /// let ids = parser.merge(logging);
+    /// let result = parser.parse(None)?;
+    ///
+    /// result.has_with_id(ids[&verbose]);
+    /// ```
+    pub fn merge(&mut self, other: Parser) -> HashMap<TemplateId, TemplateId> {
+        let mut ids = HashMap::new();
+        for template in other.ordered_templates() {
+            let mut template = template.clone();
+            if let Some(parent) = template.subargument_of {
+                let Some(&parent) = ids.get(&parent) else {
+                    continue;
+                };
+                template.subarg(parent);
+            }
+            ids.insert(template.id, self.add_to_map(template));
+        }
+        self.duplicates.extend(other.duplicates);
+        self.invalid_names.extend(other.invalid_names);
+
+        let translate = |group: &Vec<TemplateId>| -> Vec<TemplateId> {
+            group.iter().filter_map(|id| ids.get(id).copied()).collect()
+        };
+        let exclusive: Vec<_> = other.exclusive_groups.iter().map(translate).collect();
+        let required: Vec<_> = other.required_groups.iter().map(translate).collect();
+        let commands = translate(&other.commands);
+        self.exclusive_groups.extend(exclusive);
+        self.required_groups.extend(required);
+        self.commands.extend(commands);
+
+        ids
+    }
+
+    /// Return the first alias of each template with the given IDs.
+    fn names_of(&self, ids: &[TemplateId]) -> Vec<String> {
+        let templates = self.unique_templates();
+        ids.iter()
+            .filter_map(|id| templates.iter().find(|t| t.id == *id))
+            .map(|t| t.matches[0].clone())
+            .collect()
+    }
+
+    /// Remove a **top-level** `Template`, given one of its aliases. Return whether the
+    /// `Template` was removed.
+    ///
+    /// A `Template` which has subcommands is not removed, use `remove_recursive()` instead.
+    pub fn remove<S: AsRef<str>>(&mut self, key: S) -> bool {
+        match self
+            .stored
+            .get(&(0, self.key_name(key.as_ref()).to_string()))
+        {
+            Some(template) => self.remove_with_id(template.id),
+            None => false,
+        }
+    }
+
+    /// Remove a `Template`, given its ID. Return whether the `Template` was removed.
+    ///
+    /// A `Template` which has subcommands is not removed, use `remove_recursive()` instead.
+    pub fn remove_with_id(&mut self, id: TemplateId) -> bool {
+        if self.stored.values().any(|t| t.subargument_of == Some(id)) {
+            return false;
+        }
+
+        self.remove_ids(&[id])
+    }
+
+    /// Remove a `Template` together with all of its subcommands, given its ID. Return whether
+    /// the `Template` was removed.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// let db = parser.add("db", 0, "Database commands.");
+    /// parser.add_subcommand(db, "--url", 1, "Database URL.")?;
+    ///
+    /// assert!(!parser.remove_with_id(db));
+    /// assert!(parser.remove_recursive(db));
+    /// ```
+    pub fn remove_recursive(&mut self, id: TemplateId) -> bool {
+        let mut ids = vec![id];
+        let mut index = 0;
+        while index < ids.len() {
+            let parent = ids[index];
+            for template in self.unique_templates() {
+                if template.subargument_of == Some(parent) {
+                    ids.push(template.id);
+                }
+            }
+            index += 1;
+        }
+
+        self.remove_ids(&ids)
+    }
+
+    fn remove_ids(&mut self, ids: &[TemplateId]) -> bool {
+        let before = self.stored.len();
+        self.stored.retain(|_, t| !ids.contains(&t.id));
+        if before == self.stored.len() {
+            return false;
+        }
+
+        self.order.retain(|id| !ids.contains(id));
+
+        true
+    }
+
+    /// Check the added templates for mistakes, which would otherwise result in surprising
+    /// behavior when parsing.
+    ///
+    /// Returns `HpError::InvalidMatchName`, if an alias is empty or contains whitespace, such an
+    /// alias is never matched. Returns `HpError::MultipleRest`, if more than one template takes
+    /// the rest of the arguments. Returns `HpError::DuplicateMatch`, if an alias is used by
+    /// two templates in the same context, in which case the latter template replaces the former.
+    /// Returns
+    /// `HpError::UnknownParent`, if a subcommand was added to a template ID which does not exist.
+    ///
+    /// Otherwise returns a list of warnings, which don't prevent parsing, such as a subcommand
+    /// alias shadowing a top level alias, which is then not reachable in the subcommand context,
+    /// or contradicting options of a template, such as optional values of a required template.
+    ///
+    /// This method is called at the start of `parse()`, which ignores the warnings.
+    ///
+    /// ```ignore
+    /// for warning in parser.validate()? {
+    ///     eprintln!("{warning}");
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<Vec<String>, HpError> {
+        if let Some(name) = self.invalid_names.first() {
+            return Err(HpError::InvalidMatchName(name.clone()));
+        }
+        if let Some(name) = self.duplicates.first() {
+            return Err(HpError::DuplicateMatch(name.clone()));
+        }
+
+        let templates = self.unique_templates();
+        let rest: Vec<TemplateId> = templates.iter().filter(|t| t.rest).map(|t| t.id).collect();
+        if rest.len() > 1 {
+            return Err(HpError::MultipleRest(self.names_of(&rest)));
+        }
+
+        let mut warnings = Vec::new();
+        for template in templates.iter().filter(|t| t.optional_vals) {
+            let name = &template.matches[0];
+            if template.value_bounds().1 == 0 {
+                warnings.push(format!(
+                    "'{name}' has optional values, but it takes no values."
+                ));
+            } else if template.required {
+                warnings.push(format!(
+                    "'{name}' is required, but its values are optional."
+                ));
+            }
+        }
+        for template in templates.iter() {
+            let Some(parent) = template.subargument_of else {
+                continue;
+            };
+            let Some(parent) = templates.iter().find(|t| t.id == parent) else {
+                return Err(HpError::UnknownParent(parent));
+            };
+
+            for name in template.matches.iter() {
+                let name = self.key_name(name).to_string();
+                let shadows = [(0, name.clone()), (GLOBAL, name.clone())]
+                    .iter()
+                    .filter_map(|key| self.stored.get(key))
+                    .any(|t| t.id != template.id);
+                if shadows {
+                    warnings.push(format!(
+                        "Subcommand '{name}' of '{}' shadows the top level argument '{name}'.",
+                        parent.matches[0]
+                    ));
+                }
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Create a single line synopsis of the program from the top level templates.
+    ///
+    /// This is used as the usage in the help message, unless one is set with `with_usage()`.
+    /// Required arguments are not enclosed in brackets.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().with_program_name("myprog");
+    /// parser.add("--say", 1, "Say something.");
+    /// parser.add("-x", 0, "A flag.");
+    ///
+    /// assert_eq!(parser.synopsis(), "myprog [--say <value>] [-x]");
+    /// ```
+    pub fn synopsis(&self) -> String {
+        let templates = self.ordered_templates();
+        let mut result = self.program_name.clone();
+
+        for template in templates
+            .iter()
+            .filter(|t| t.subargument_of.is_none() && !t.hidden)
+        {
+            let mut argument = template.matches[0].clone();
+            let (min, max) = template.value_bounds();
+            let values = match (min, max) {
+                (_, 0) => String::new(),
+                (_, usize::MAX) => " <value>...".to_string(),
+                (min, max) if min != max => " <value>...".to_string(),
+                (n, _) => " <value>".repeat(n),
+            };
+            if template.optional_vals && !values.is_empty() {
+                write!(argument, " [{}]", values.trim_start()).unwrap_or(());
+            } else {
+                argument.push_str(&values);
+            }
+            if templates
+                .iter()
+                .any(|t| t.subargument_of == Some(template.id))
+            {
+                argument.push_str(" <subcommand>");
+            }
+
+            match template.required {
+                true => write!(result, " {argument}").unwrap_or(()),
+                false => write!(result, " [{argument}]").unwrap_or(()),
+            }
+        }
+
+        result
+    }
+
+    /// Replace the placeholders in the usage set with `with_usage()`.
+    pub(crate) fn expand_usage(&self) -> String {
+        let mut result = String::new();
+        let mut rest = self.usage.as_str();
+
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            let name = &rest[1..end];
+            if name == "prog" {
+                result.push_str(&self.program_name);
+            } else if let Some(template) = self.stored.get(&(0, self.key_name(name).to_string())) {
+                result.push_str(name);
+                if let Some(values) = template.values_label() {
+                    write!(result, " {values}").unwrap_or(());
+                }
+            } else {
+                result.push_str(&rest[..=end]);
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+
+        result
+    }
+
+    /// Create the help message of the whole program, or only of the subcommands of `root`.
+    fn create_help(&self, root: Option<TemplateId>) -> String {
+        let mut result_string = String::new();
+        let root_template = root.and_then(|id| self.stored.values().find(|t| t.id == id));
+
+        let longest_value_len = self
+            .ordered_templates()
+            .into_iter()
+            .filter(|t| !t.hidden)
+            .map(|t| t.help_label().len())
+            .max();
+
+        if let Some(template) = root_template {
+            let mut name = self.program_name.clone();
+            for ancestor in self.contexts(template.id).iter().skip(1).rev() {
+                if let Some(ancestor) = self.stored.values().find(|t| t.id == *ancestor) {
+                    write!(name, " {}", ancestor.matches[0]).unwrap_or(());
+                }
+            }
+            write!(name, " {}", template.matches.join(" | ")).unwrap_or(());
+            writeln!(result_string, "{}: {}", name.trim_start(), template.help).unwrap_or(());
+        } else {
+            if !self.program_name.is_empty() {
+                write!(result_string, "{}", self.program_name).unwrap_or(());
+            }
+            if !self.description.is_empty() {
+                writeln!(result_string, ": {}", self.description).unwrap_or(());
+            }
+            if !self.author.is_empty() {
+                writeln!(result_string, "Author: {}", self.author).unwrap_or(());
+            }
+        }
+        if self.show_usage && root_template.is_none() {
+            match self.usage.is_empty() {
+                false => {
+                    writeln!(result_string, "Usage:\n    {}", self.expand_usage()).unwrap_or(())
+                }
+                true => writeln!(result_string, "Usage:\n    $ {}", self.synopsis()).unwrap_or(()),
+            }
+        }
+
+        let longest_value_len = match longest_value_len {
+            Some(l) => l + 4,
+            None => 4,
+        };
+        let mut max_level = 0;
+
+        let mut children: HashMap<Option<usize>, Vec<&Template>> = HashMap::new();
+        for template in self.ordered_templates().into_iter().filter(|t| !t.hidden) {
+            children
+                .entry(template.subargument_of)
+                .or_default()
+                .push(template);
+        }
+        if self.sort_help == SortMode::Alphabetical {
+            for templates in children.values_mut() {
+                templates
+                    .sort_by_cached_key(|t| t.matches[0].trim_start_matches('-').to_lowercase());
+            }
+        }
+
+        type Section<'a> = (Option<&'a str>, Vec<(&'a Template, usize)>);
+        let mut sections: Vec<Section> = vec![(None, Vec::new())];
+        for top in children.get(&root).cloned().unwrap_or_default() {
+            let group = top.group.as_deref();
+            let section = match sections.iter().position(|(name, _)| *name == group) {
+                Some(position) => &mut sections[position].1,
+                None => {
+                    sections.push((group, Vec::new()));
+                    &mut sections.last_mut().unwrap().1
+                }
+            };
+
+            let mut stack = vec![(top, 0)];
+            while let Some((template, level)) = stack.pop() {
+                max_level = max_level.max(level);
+                section.push((template, level));
+                if let Some(subs) = children.get(&Some(template.id)) {
+                    stack.extend(subs.iter().rev().map(|t| (*t, level + 1)));
+                }
+            }
+        }
+
+        if self.sort_help == SortMode::Grouped {
+            sections[1..].sort_by_key(|(name, _)| *name);
+        }
+
+        let width = longest_value_len + max_level * 4;
+        let indent = width + 5;
+        let total_width = self.help_width.or_else(term::term_width).unwrap_or(80);
+        let help_width = total_width.saturating_sub(indent).max(20);
+        for (i, (group, template_vec)) in sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(result_string, "{}:", group.unwrap_or_default()).unwrap_or(());
+            } else {
+                writeln!(result_string, "Arguments:").unwrap_or(());
+            }
+
+            for (template, level) in template_vec.iter() {
+                let lvl = " ".repeat(level * 4);
+                let matches = template.help_label();
+                let padding = width.saturating_sub(lvl.len());
+
+                let mut lines = wrap(&template.help, help_width).into_iter();
+                writeln!(
+                    result_string,
+                    "    {lvl}{matches:<padding$} {}",
+                    lines.next().unwrap_or_default()
+                )
+                .unwrap_or(());
+                for line in lines {
+                    writeln!(result_string, "{:indent$}{line}", "").unwrap_or(());
+                }
+            }
+
+            if i > 0 {
+                continue;
+            }
+            if self.auto_help && !self.help_flags.is_empty() {
+                let help = self.help_flags.join(", ");
+                writeln!(result_string, "    {help:<width$} Print this help message!")
+                    .unwrap_or(());
+            }
+            if self.version.is_some() && !self.version_flags.is_empty() {
+                let version = self.version_flags.join(", ");
+                writeln!(
+                    result_string,
+                    "    {version:<width$} Print version information."
+                )
+                .unwrap_or(());
+            }
+        }
+        let examples = match root_template {
+            Some(_) => &[][..],
+            None => &self.examples[..],
+        };
+        if !examples.is_empty() {
+            writeln!(result_string, "Examples:").unwrap_or(());
+        }
+        for (invocation, description) in examples.iter() {
+            writeln!(result_string, "    $ {invocation}").unwrap_or(());
+            for line in wrap(description, total_width.saturating_sub(8).max(20)) {
+                writeln!(result_string, "        {line}").unwrap_or(());
+            }
+        }
+        if !self.footer.is_empty() && root_template.is_none() {
+            writeln!(result_string, "{}", self.footer).unwrap_or(());
+        }
+        if result_string.ends_with('\n') {
+            result_string.pop();
+        }
+
+        result_string
+    }
+
+    /// Return the help message, either the one set with `set_help()`, or a generated one.
+    ///
+    /// Nothing is printed and the program doesn't exit, regardless of `exit_on_help()`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new().with_program_name("myprog");
+    /// parser.add("--say", 1, "Say something.");
+    ///
+    /// assert!(parser.help_string().contains("--say"));
+    /// ```
+    pub fn help_string(&self) -> String {
+        match &self.help {
+            Some(help) => help.clone(),
+            None => self.create_help(None),
+        }
+    }
+
+    /// Write the help message, followed by a newline, to the given writer.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new().with_program_name("myprog");
+    /// parser.print_help(&mut std::io::stderr())?;
+    /// ```
+    pub fn print_help<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "{}", self.help_string())
+    }
+
+    /// Print the help message and exit, or return it, see `exit_on_help()`. The help message is
+    /// scoped to the given context, if `contextual_help()` is set.
+    fn help_and_exit(&self, context: usize) -> HpError {
+        let help = match self.contextual_help && self.help.is_none() {
+            true => match self.help_root(context) {
+                Some(root) => self.create_help(Some(root)),
+                None => self.help_string(),
+            },
+            false => self.help_string(),
+        };
+        if self.exit_on_help {
+            println!("{help}");
+            exit(0);
+        }
+
+        HpError::HelpRequested(help)
+    }
+
+    /// Find the nearest template with subcommands, starting at the given context and continuing
+    /// with its ancestors.
+    fn help_root(&self, context: usize) -> Option<TemplateId> {
+        self.contexts(context).into_iter().find(|&id| {
+            id != 0
+                && self
+                    .stored
+                    .values()
+                    .any(|t| t.subargument_of == Some(id) && !t.hidden)
+        })
+    }
+
+    fn version_and_exit(&self, version: &str) -> HpError {
+        let version_string = format!("{} {version}", self.program_name);
+
+        if self.exit_on_version {
+            println!("{version_string}");
+            exit(0);
+        }
+
+        HpError::VersionRequested(version_string)
+    }
+
+    /// Describe all the added templates, in the order they were added.
+    ///
+    /// ```ignore
+    /// for info in parser.templates() {
+    ///     println!("{}: {}", info.matches.join(", "), info.help);
+    /// }
+    /// ```
+    pub fn templates(&self) -> Vec<TemplateInfo> {
+        self.ordered_templates()
+            .into_iter()
+            .map(TemplateInfo::from)
+            .collect()
+    }
+
+    /// Return every stored template once, in the order they were added.
+    fn ordered_templates(&self) -> Vec<&Template> {
+        let by_id: HashMap<TemplateId, &Template> =
+            self.stored.values().map(|t| (t.id, t)).collect();
+        self.order
+            .iter()
+            .filter_map(|id| by_id.get(id).copied())
+            .collect()
+    }
+
+    /// Return every stored template once, ordered by its ID.
+    pub(crate) fn unique_templates(&self) -> Vec<&Template> {
+        let mut templates: Vec<&Template> = self.stored.values().collect();
+        templates.sort_by_key(|t| t.id);
+        templates.dedup_by_key(|t| t.id);
+        templates
+    }
+
+    /// Return the name under which an alias is stored, without the leading dashes, if
+    /// `normalize_dashes()` is set.
+    fn key_name<'a>(&self, name: &'a str) -> &'a str {
+        match self.normalize_dashes {
+            true => strip_dashes(name),
+            false => name,
+        }
+    }
+
+    /// Find the template matching `name`, first in the given context, then at the top level and
+    /// finally among the global templates. Return the key under which the template is stored
+    /// together with the template.
+    fn lookup(&self, context: usize, name: &str) -> Option<(Key, &Template)> {
+        let name = self.key_name(name);
+        for context in self.contexts(context) {
+            let query = (context, name.to_string());
+            if let Some(template) = self.stored.get(&query) {
+                return Some((query, template));
+            }
+        }
+
+        let template = self.stored.get(&(GLOBAL, name.to_string()))?;
+        let query = (template.subargument_of.unwrap_or(0), name.to_string());
+        Some((query, template))
+    }
+
+    /// Return the given context followed by the contexts of its ancestors, ending with the top
+    /// level context, in which templates are looked up.
+    fn contexts(&self, context: usize) -> Vec<usize> {
+        let mut contexts = vec![context];
+        let mut parent = Some(context).filter(|c| *c != 0);
+        while let Some(t) = parent.and_then(|id| self.stored.values().find(|t| t.id == id)) {
+            parent = t.subargument_of;
+            contexts.push(parent.unwrap_or(0));
+        }
+        if contexts.last() != Some(&0) {
+            contexts.push(0);
+        }
+
+        contexts
+    }
+
+    /// Find the long argument available in the given context, which `name` is an abbreviation
+    /// of.
+    fn unabbreviate(&self, context: usize, name: &str) -> Result<Option<String>, HpError> {
+        if !self.abbreviations || !name.starts_with("--") {
+            return Ok(None);
+        }
+
+        let mut candidates: Vec<&str> = self
+            .stored
+            .keys()
+            .filter(|(c, _)| *c == GLOBAL || self.contexts(context).contains(c))
+            .map(|(_, candidate)| candidate.as_str())
+            .filter(|candidate| candidate.starts_with(self.key_name(name)))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        match candidates.len() {
+            0 => Ok(None),
+            1 => Ok(Some(candidates[0].to_string())),
+            _ => Err(HpError::AmbiguousArgument(
+                name.to_string(),
+                candidates.iter().map(|c| c.to_string()).collect(),
+            )),
+        }
+    }
+
+    /// Find the negatable flag available in the given context, which `name` is the negation of.
+    fn lookup_negated(&self, context: usize, name: &str) -> Option<(Key, &Template)> {
+        let name = format!("--{}", name.strip_prefix("--no-")?);
+        self.lookup(context, &name)
+            .filter(|(_, template)| template.negatable && template.value_bounds().1 == 0)
+    }
+
+    fn is_template(&self, context: usize, name: &str) -> bool {
+        self.lookup(context, name).is_some()
+    }
+
+    /// Check whether `token` is a known argument in the given context, a negation of one or a
+    /// bundle of flags.
+    fn is_argument(&self, context: usize, token: &Token) -> bool {
+        self.is_template(context, &token.raw)
+            || self.is_template(context, &token.name)
+            || self.lookup_negated(context, &token.name).is_some()
+            || self.bundled_flags(context, token).is_some()
+    }
+
+    /// Decide, whether `token` can be collected as a value of `template` in the given context.
+    fn is_value(&self, template: &Template, context: usize, token: &Token) -> bool {
+        if token.raw == "--"
+            || template.stop_at.contains(&token.raw)
+            || self.is_help_flag(&token.raw)
+            || self.is_version_flag(&token.raw)
+        {
+            return false;
+        }
+        if template.dash_values {
+            return true;
+        }
+        if template.greedy {
+            return [&token.raw, &token.name].iter().all(|name| {
+                let name = self.key_name(name).to_string();
+                !self.stored.contains_key(&(0, name.clone()))
+                    && !self.stored.contains_key(&(GLOBAL, name))
+            });
+        }
+        if self.is_template(context, &token.raw)
+            || self.lookup_negated(context, &token.name).is_some()
+        {
+            return false;
+        }
+        if template.negative_numbers && is_negative_number(&token.raw) {
+            return true;
+        }
+
+        !self.is_template(context, &token.name) && self.bundled_flags(context, token).is_none()
+    }
+
+    /// Check whether `raw` requests the help message, see `help_flags()`.
+    fn is_help_flag(&self, raw: &str) -> bool {
+        self.auto_help && self.help_flags.iter().any(|flag| flag == raw)
+    }
+
+    /// Check whether `raw` requests the version, see `version_flags()`.
+    fn is_version_flag(&self, raw: &str) -> bool {
+        self.version.is_some() && self.version_flags.iter().any(|flag| flag == raw)
+    }
+
+    /// Split a bundle of short flags, such as `-abc`, into separate flags, if every one of them
+    /// is a known template which takes no values.
+    fn bundled_flags(&self, context: usize, token: &Token) -> Option<Vec<Token>> {
+        let flags = token.raw.strip_prefix('-')?;
+        if flags.starts_with('-') || flags.chars().count() < 2 || token.inline.is_some() {
+            return None;
+        }
+
+        flags
+            .chars()
+            .map(|c| {
+                let name = format!("-{c}");
+                let (_, template) = self.lookup(context, &name)?;
+                (template.num_values == 0).then(|| Token::new(token.position, name))
+            })
+            .collect()
+    }
+
+    /// Parse the command line arguments, or a list of strings, if provided, and return a
+    /// `ParsedArguments` structure.
+    ///
+    /// When parsing the command line arguments, the first one, which is the name of the program,
+    /// is skipped. A provided list of strings is parsed as is, so it should only contain the
+    /// actual arguments, `parser.parse(Some(vec!["--say", "hello"]))`.
+    ///
+    /// Values can either follow the argument as separate entries (`--say hello`) or be attached
+    /// to it with an equals sign (`--say=hello`). An attached value counts as the first value of
+    /// the argument, the rest of the values are read from the following entries.
+    ///
+    /// Single dash flags which take no values can be bundled together, so `-abc` is the same as
+    /// `-a -b -c`. If any of the bundled flags is unknown or takes values, the whole argument is
+    /// treated as a single unknown argument. Just like any other known argument, a bundle of flags
+    /// ends the values of the preceding argument, see `Template::allow_negative_numbers()`.
+    ///
+    /// A standalone `--` ends the parsing, all the arguments after it are available through
+    /// `ParsedArguments::trailing()`.
+    ///
+    /// The parser keeps no state between parses, so it can parse any number of command lines,
+    /// such as the lines read by a long-running process, and each result only describes its own
+    /// line, folds included. Only the actions keep whatever state their closures capture, since
+    /// they run during every parse.
+    pub fn parse(&self, from: Option<Vec<&str>>) -> Result<ParsedArguments, HpError> {
+        self.parse_from(collect_args(from))
+    }
+
+    /// Parse the command line arguments and return the result, or exit the program.
+    ///
+    /// An error is printed to stderr, with the argument which caused it underlined, and the
+    /// program exits with the code set by `error_exit_code()`, 2 by default. The help message and
+    /// the version are printed to stdout and the program exits with 0, regardless of
+    /// `exit_on_help()` and `exit_on_version()`.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// parser.add("--say", 1, "Say something.");
+    /// let args = parser.parse_or_exit();
+    /// ```
+    pub fn parse_or_exit(&self) -> ParsedArguments {
+        let args = collect_args(None);
+        match self.parse_from(args.clone()) {
+            Ok(result) => result,
+            Err(HpError::HelpRequested(message) | HpError::VersionRequested(message)) => {
+                println!("{message}");
+                exit(0);
+            }
+            Err(err) => {
+                // The positions of the errors index the arguments with the response files
+                // expanded.
+                let args = match self.response_files {
+                    true => expand_response_files(args.clone()).unwrap_or(args),
+                    false => args,
+                };
+                eprintln!("{}", err.pretty(&args));
+                exit(self.error_exit_code);
+            }
+        }
+    }
+
+    /// Parse any list of owned strings, such as arguments read from a file, and return a
+    /// `ParsedArguments` structure.
+    ///
+    /// Just like a list provided to `parse()`, the list should only contain the actual arguments,
+    /// without the name of the program.
+    ///
+    /// ```ignore
+    /// let args = std::fs::read_to_string("args.txt")?;
+    /// let result = parser.parse_from(args.split_whitespace().map(String::from))?;
+    /// ```
+    pub fn parse_from<I: IntoIterator<Item = String>>(
+        &self,
+        args: I,
+    ) -> Result<ParsedArguments, HpError> {
+        self.parse_args(args.into_iter().collect(), true)
+            .map(|(result, _)| result)
+    }
+
+    /// Split a single line into arguments, like a shell would, and parse them.
+    ///
+    /// Arguments are separated by whitespace. Quotes, both single and double, and backslashes can
+    /// be used to include whitespace in an argument. An unterminated quote results in
+    /// `HpError::UnterminatedQuote`.
+    ///
+    /// ```ignore
+    /// let result = parser.parse_str(r#"--say "hello world" --verbose"#)?;
+    /// assert_eq!(result.get("--say").unwrap().values(), &vec!["hello world"]);
+    /// ```
+    pub fn parse_str(&self, line: &str) -> Result<ParsedArguments, HpError> {
+        self.parse_from(shell::split(line)?)
+    }
+
+    /// Parse the arguments like `parse()`, but only run the action of the deepest matched
+    /// subcommand, instead of the actions of all the matched templates.
+    ///
+    /// The action receives the values of the subcommand followed by the positional arguments. If
+    /// more subcommands are equally deep, the action of the last one runs.
+    ///
+    /// ```ignore
+    /// let mut parser = Parser::new();
+    /// let remote = parser.add("remote", 0, "Manage remotes.");
+    /// parser.add_subcommand_template(remote, Template::new()
+    ///     .matches("add")
+    ///     .on_parse(|args| println!("Adding remote '{}' at '{}'.", args[0], args[1])))?;
+    ///
+    /// // $ myprog remote add origin url
+    /// // Only the action of 'add' runs, with the arguments 'origin' and 'url'.
+    /// parser.dispatch(None)?;
+    /// ```
+    pub fn dispatch(&self, from: Option<Vec<&str>>) -> Result<(), HpError> {
+        let (result, matched) = self.parse_args(collect_args(from), false)?;
+        let templates = self.unique_templates();
+        let deepest = matched
+            .iter()
+            .filter_map(|id| templates.iter().find(|t| t.id == *id))
+            .max_by_key(|t| self.depth(t));
+        let Some(template) = deepest else {
+            return Ok(());
+        };
+
+        let mut values = result
+            .get_with_id(template.id)
+            .map(|arg| arg.values().clone())
+            .unwrap_or_default();
+        values.extend(result.positionals().iter().cloned());
+        template.run_action(&template.matches[0], &values)
+    }
+
+    /// Return the number of ancestors of `template`, zero for a top level template.
+    fn depth(&self, template: &Template) -> usize {
+        let mut depth = 0;
+        let mut parent = template.subargument_of;
+        while let Some(t) = parent.and_then(|id| self.stored.values().find(|t| t.id == id)) {
+            depth += 1;
+            parent = t.subargument_of;
+        }
+
+        depth
+    }
+
+    /// Print a message explaining a decision of the parser to stderr, or pass it to the
+    /// `on_explain()` function if there is one, if `explain()` is set.
+    fn trace(&self, message: std::fmt::Arguments) {
+        if !self.explain {
+            return;
+        }
+        if self.on_explain.0.is_some() {
+            let _ = self.on_explain.call(&message.to_string(), 0, Vec::new());
+        } else {
+            let (_, green, _, none) = errors::colors(false);
+            eprintln!("{green}explain{none}: {message}");
+        }
+    }
+
+    /// Map every alias of a parsed template to its ID, so that the parsed argument can be
+    /// retrieved by any of them.
+    fn insert_aliases(
+        &self,
+        aliases: &mut HashMap<Key, TemplateId>,
+        context: usize,
+        template: &Template,
+    ) {
+        for name in template.matches.iter() {
+            aliases.insert((context, self.key_name(name).to_string()), template.id);
+        }
+    }
+
+    /// Warn about a deprecated alias, through the `on_deprecation()` function if there is one.
+    fn warn_deprecated(&self, name: &str, replacement: &str) {
+        if self.on_deprecation.0.is_some() {
+            let _ = self
+                .on_deprecation
+                .call(name, 0, vec![replacement.to_string()]);
+        } else if replacement.is_empty() {
+            eprintln!("warning: '{name}' is deprecated");
+        } else {
+            eprintln!("warning: '{name}' is deprecated, use '{replacement}'");
+        }
+    }
+
+    /// Parse the arguments, return the result and the IDs of the matched templates in the order
+    /// in which they were matched.
+    fn parse_args(
+        &self,
+        args: Vec<String>,
+        run_actions: bool,
+    ) -> Result<(ParsedArguments, Vec<TemplateId>), HpError> {
+        self.validate()?;
+        let args = match self.response_files {
+            true => expand_response_files(args)?,
+            false => args,
+        };
+        let mut tokens: Vec<Token> = args
+            .into_iter()
+            .enumerate()
+            .map(|(position, arg)| Token::new(position, arg))
+            .collect();
+
+        let mut hm = HashMap::new();
+        let mut aliases = HashMap::new();
+        let mut idhm: HashMap<TemplateId, ParsedArgument> = HashMap::new();
+        let mut counts = HashMap::new();
+        let mut folded: HashMap<TemplateId, Box<AnyValue>> = HashMap::new();
+        let mut trailing = Vec::new();
+        let mut positionals = Vec::new();
+        let mut unknown = Vec::new();
+        let mut command: Option<TemplateId> = None;
+        let mut subcommand = None;
+
+        let mut context = 0;
+        let mut index = 0;
+        let mut matched = Vec::new();
+        let mut stats = ParseStats::default();
+
+        while index < tokens.len() {
+            let token = &tokens[index];
+            if self.is_help_flag(&token.raw) {
+                return Err(self.help_and_exit(context));
+            }
+            if let Some(version) = self
+                .version
+                .as_ref()
+                .filter(|_| self.is_version_flag(&token.raw))
+            {
+                return Err(self.version_and_exit(version));
+            }
+            if token.raw == "--" {
+                trailing = tokens[index + 1..].iter().map(|t| t.raw.clone()).collect();
+                self.trace(format_args!(
+                    "'--' ends the arguments, {} trailing argument/s",
+                    trailing.len()
+                ));
+                break;
+            }
+
+            let found = match self.lookup(context, &token.name) {
+                Some(found) => Some(found),
+                None => self
+                    .unabbreviate(context, &token.name)?
+                    .and_then(|name| self.lookup(context, &name)),
+            };
+            let mut negated = false;
+            let found = found.or_else(|| {
+                let found = self.lookup_negated(context, &token.name)?;
+                negated = true;
+                Some(found)
+            });
+            let found = found.filter(|(_, template)| {
+                !self.commands.contains(&template.id)
+                    || (command.is_none() && positionals.is_empty())
+            });
+            let (query, template) = if let Some(found) = found {
+                found
+            } else {
+                if let Some(flags) = self.bundled_flags(context, token) {
+                    if self.explain {
+                        let names: Vec<&str> = flags.iter().map(|f| f.raw.as_str()).collect();
+                        self.trace(format_args!(
+                            "'{}' is split into '{}'",
+                            token.raw,
+                            names.join("', '")
+                        ));
+                    }
+                    tokens.splice(index..=index, flags);
+                    continue;
+                }
+                if let Some(template) = self.unique_templates().into_iter().find(|t| {
+                    t.matches
+                        .iter()
+                        .any(|m| self.key_name(m) == self.key_name(&token.name))
+                }) {
+                    if let Some(parent) = template.subargument_of {
+                        let parent = self
+                            .stored
+                            .values()
+                            .find(|t| t.id == parent)
+                            .ok_or(HpError::UnknownParent(parent))?;
+                        return Err(HpError::OutOfContext(
+                            token.name.clone(),
+                            parent.matches[0].clone(),
+                            token.position,
+                        ));
+                    }
+                }
+                if self.capture_unknown && token.raw.starts_with('-') && token.raw != "-" {
+                    self.trace(format_args!(
+                        "'{}' is not an argument in context {context}, it's captured",
+                        token.raw
+                    ));
+                    unknown.push(token.raw.clone());
+                    index += 1;
+                    continue;
+                }
+                if self.strict && token.raw.starts_with('-') && token.raw != "-" {
+                    let templates = self.ordered_templates();
+                    let names = templates
+                        .iter()
+                        .flat_map(|t| t.matches.iter().map(|s| s.as_str()));
+                    let suggestion = suggest::closest(&token.name, names, 2);
+                    return Err(HpError::UnknownArgument(
+                        token.name.clone(),
+                        suggestion.map(|s| s.to_string()),
+                        token.position,
+                    ));
+                }
+                if !token.raw.starts_with('-') || token.raw == "-" {
+                    self.trace(format_args!(
+                        "'{}' is not an argument in context {context}, it's a positional argument",
+                        token.raw
+                    ));
+                    positionals.push(token.raw.clone());
+                } else {
+                    self.trace(format_args!(
+                        "'{}' is not an argument in context {context}, it's ignored",
+                        token.raw
+                    ));
+                }
+                index += 1;
+                continue;
+            };
+
+            if self.explain {
+                let place = match query.0 {
+                    c if c == context => format!("in the current context {c}"),
+                    0 => "at the top level".to_string(),
+                    c => format!("in the ancestor context {c}"),
+                };
+                self.trace(format_args!(
+                    "'{}' matches the key ({}, '{}') {place}",
+                    token.raw, query.0, query.1
+                ));
+            }
+
+            if self.commands.contains(&template.id) {
+                command = Some(template.id);
+                subcommand = Some(token.name.clone());
+            }
+            // The ancestors of the matched template stay reachable, a template outside of the
+            // matched command, such as a top-level flag, keeps the current context.
+            context = match command {
+                Some(command) if !self.contexts(template.id).contains(&command) => context,
+                _ => template.id,
+            };
+            matched.push(template.id);
+            stats.matched_count += 1;
+            stats.max_context_depth = stats.max_context_depth.max(self.depth(template));
+            let start = index;
+            let mut values: Vec<String> = Vec::new();
+
+            let (min, max) = template.value_bounds();
+
+            if let Some(value) = &token.inline {
+                if max == 0 {
+                    return Err(HpError::NumberOfValues(
+                        token.name.clone(),
+                        1,
+                        0,
+                        token.position,
+                    ));
+                }
+                template.push_value(&mut values, value);
+            }
+
+            let mut stopped_by = None;
+            if template.rest {
+                values.extend(tokens[index + 1..].iter().map(|t| t.raw.clone()));
+                index = tokens.len() - 1;
+            }
+            while values.len() < max && index + 1 < tokens.len() {
+                let next = &tokens[index + 1];
+                if !self.is_value(template, context, next) {
+                    stopped_by = Some(next).filter(|next| next.raw != "--");
+                    break;
+                }
+                template.push_value(&mut values, &next.raw);
+                index += 1;
+            }
+            if stopped_by
+                .is_some_and(|next| self.is_help_flag(&next.raw) || self.is_version_flag(&next.raw))
+            {
+                // The help or the version is requested, which takes precedence over the values.
+                index += 1;
+                continue;
+            }
+            if template.exact_values && !template.rest && values.len() == max {
+                let extra = tokens[index + 1..]
+                    .iter()
+                    .take_while(|next| {
+                        !next.raw.starts_with('-') && self.is_value(template, context, next)
+                    })
+                    .count();
+                if extra > 0 {
+                    return Err(HpError::TooManyValues(
+                        token.name.clone(),
+                        max + extra,
+                        max,
+                        token.position,
+                    ));
+                }
+            }
+            if self.explain {
+                let reason = match stopped_by {
+                    _ if template.rest => "it takes the rest of the arguments".to_string(),
+                    Some(next) => format!("'{}' is an argument", next.raw),
+                    None if values.len() >= max => format!("the maximum is {max}"),
+                    None if index + 1 >= tokens.len() => "there are no more arguments".to_string(),
+                    None => "of the '--' separator".to_string(),
+                };
+                self.trace(format_args!(
+                    "'{}' takes {} value/s, the values end because {reason}",
+                    token.name,
+                    values.len()
+                ));
+            }
+
+            if let Some(flag) = stopped_by.filter(|next| {
+                !template.optional_vals && values.len() < min && self.is_argument(context, next)
+            }) {
+                return Err(HpError::ValueIsFlag(
+                    token.name.clone(),
+                    flag.raw.clone(),
+                    token.position,
+                ));
+            }
+            template.check_number_of_values(&token.name, values.len(), token.position)?;
+
+            template.check_choices(&token.name, &values)?;
+            template.check_key_values(&values)?;
+            template.check_each(&token.name, &values)?;
+
+            let supplied = !values.is_empty();
+            if !supplied {
+                values = template.defaults.clone();
+            }
+
+            let alias = match negated {
+                true => &token.name,
+                false => template
+                    .matches
+                    .iter()
+                    .find(|m| self.key_name(m) == query.1)
+                    .unwrap_or(&query.1),
+            };
+            if let Some(replacement) = template.deprecated.as_ref().filter(|_| !negated) {
+                self.warn_deprecated(alias, replacement);
+            }
+            if run_actions && !negated {
+                template.run_action(alias, &values)?;
+            }
+
+            if !negated {
+                let state = folded.remove(&template.id);
+                if let Some(state) = template.fold.call(state, &values) {
+                    folded.insert(template.id, state);
+                }
+                *counts.entry(template.id).or_insert(0) += 1;
+            }
+
+            if template.key_value {
+                if let Some(previous) = idhm.get(&template.id) {
+                    values.splice(0..0, previous.values.iter().cloned());
+                }
+            }
+
+            let pa = ParsedArgument {
+                supplied,
+                negated,
+                matched: token.name.clone(),
+                parent: template.subargument_of,
+                ..ParsedArgument::new(template.id, values)
+            };
+            self.insert_aliases(&mut aliases, query.0, template);
+            hm.insert(query, pa.clone());
+            idhm.insert(template.id, pa);
+            index += 1;
+            stats.tokens_consumed += index - start;
+        }
+
+        let config = match &self.config_file {
+            Some(path) => config::read(path)?,
+            None => HashMap::new(),
+        };
+        for template in self.unique_templates() {
+            if idhm.contains_key(&template.id)
+                || !template
+                    .subargument_of
+                    .is_none_or(|parent| idhm.contains_key(&parent))
+            {
+                continue;
+            }
+            let value = template
+                .matches
+                .iter()
+                .find_map(|m| config.get(strip_dashes(m)).cloned())
+                .or_else(|| template.env.as_ref().and_then(|var| env::var(var).ok()))
+                .or_else(|| match template.required && self.prompts {
+                    true => template.prompt.as_deref().and_then(term::prompt),
+                    false => None,
+                });
+            let Some(value) = value else {
+                continue;
+            };
+
+            self.trace(format_args!(
+                "'{}' is not present, its value is taken from a fallback",
+                template.matches[0]
+            ));
+            let values: Vec<String> = match template.value_bounds().1 {
+                0 if value == "false" => continue,
+                0 => Vec::new(),
+                _ if template.delimiter.is_some() => {
+                    let mut values = Vec::new();
+                    template.push_value(&mut values, &value);
+                    values
+                }
+                1 => vec![value],
+                _ => value.split_whitespace().map(|v| v.to_string()).collect(),
+            };
+            let name = &template.matches[0];
+            template.check_number_of_values(name, values.len(), errors::NO_POSITION)?;
+            template.check_choices(name, &values)?;
+            template.check_key_values(&values)?;
+            template.check_each(name, &values)?;
+
+            if run_actions {
+                template.run_action(name, &values)?;
+            }
+
+            let pa = ParsedArgument {
+                matched: name.clone(),
+                parent: template.subargument_of,
+                ..ParsedArgument::new(template.id, values)
+            };
+            let context = template.subargument_of.unwrap_or(0);
+            self.insert_aliases(&mut aliases, context, template);
+            hm.insert((context, self.key_name(name).to_string()), pa.clone());
+            idhm.insert(template.id, pa);
+        }
+
+        let missing: Vec<&Template> = self
+            .unique_templates()
+            .into_iter()
+            .filter(|t| {
+                t.required
+                    && !idhm.contains_key(&t.id)
+                    && t.subargument_of
+                        .is_none_or(|parent| idhm.contains_key(&parent))
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(HpError::MissingRequired(
+                missing.iter().map(|t| t.matches[0].clone()).collect(),
+            ));
+        }
+
+        for group in self.exclusive_groups.iter() {
+            let present: Vec<TemplateId> = group
+                .iter()
+                .filter(|id| idhm.contains_key(id))
+                .copied()
+                .collect();
+            if present.len() > 1 {
+                return Err(HpError::ConflictingArguments(self.names_of(&present)));
+            }
+        }
+        for group in self.required_groups.iter() {
+            if !group.iter().any(|id| idhm.contains_key(id)) {
+                return Err(HpError::MissingOneOf(self.names_of(group)));
+            }
+        }
+
+        let result = ParsedArguments {
+            hm,
+            aliases,
+            ids: idhm,
+            counts,
+            trailing,
+            positionals,
+            unknown,
+            subcommand,
+            folded: folded
+                .into_iter()
+                .map(|(id, value)| (id, shared(value)))
+                .collect(),
+            stats,
+            normalized: self.normalize_dashes,
+        };
+
+        Ok((result, matched))
+    }
+}
+
+/// Share a value behind an `Rc<RefCell>`, or an `Arc<Mutex>` with the `sync` feature.
+fn shared<T>(value: T) -> Shared<T> {
+    #[cfg(not(feature = "sync"))]
+    let shared = std::rc::Rc::new(std::cell::RefCell::new(value));
+    #[cfg(feature = "sync")]
+    let shared = std::sync::Arc::new(std::sync::Mutex::new(value));
+
+    shared
+}
+
+/// Convert the provided list of strings to owned strings, or collect the command line arguments,
+/// without the name of the program.
+fn collect_args(from: Option<Vec<&str>>) -> Vec<String> {
+    match from {
+        Some(from_vec) => from_vec.iter().map(|each| each.to_string()).collect(),
+        None => env::args().skip(1).collect(),
+    }
+}
+
+/// Replace every argument starting with `@` with the whitespace separated contents of the file
+/// it names, up to the `--` separator.
+fn expand_response_files(args: Vec<String>) -> Result<Vec<String>, HpError> {
+    let mut result = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+
+    for arg in args.by_ref() {
+        if arg == "--" {
+            result.push(arg);
+            break;
+        }
+        let Some(path) = arg.strip_prefix('@').filter(|path| !path.is_empty()) else {
+            result.push(arg);
+            continue;
+        };
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| HpError::ResponseFileError(path.to_string(), e.to_string()))?;
+        for word in contents.split_whitespace() {
+            if word.len() > 1 && word.starts_with('@') {
+                return Err(HpError::ResponseFileError(
+                    path.to_string(),
+                    format!("nested response file '{word}' is not supported"),
+                ));
+            }
+            result.push(word.to_string());
+        }
+    }
+    result.extend(args);
+
+    Ok(result)
+}
+
+/// Strip the leading dashes of `name`, unless it consists only of dashes.
+fn strip_dashes(name: &str) -> &str {
+    match name.trim_start_matches('-') {
+        "" => name,
+        stripped => stripped,
+    }
+}
+
+/// Check whether `s` looks like a negative number, such as `-5` or `-0.5`.
+fn is_negative_number(s: &str) -> bool {
+    let Some(number) = s.strip_prefix('-') else {
+        return false;
+    };
+
+    number.chars().any(|c| c.is_ascii_digit())
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && number.matches('.').count() <= 1
+}
+
+/// Split `text` into lines no longer than `width`, breaking only at whitespace.
+///
+/// Words longer than `width` are kept whole on their own line.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// A single command line argument, split into a name and an attached value, if the
+/// `--name=value` syntax was used.
+struct Token {
+    raw: String,
+    name: String,
+    inline: Option<String>,
+    position: usize,
+}
+
+impl Token {
+    fn new(position: usize, raw: String) -> Self {
+        if raw.starts_with('-') {
+            if let Some((name, value)) = raw.split_once('=') {
+                return Self {
+                    name: name.to_string(),
+                    inline: Some(value.to_string()),
+                    raw,
+                    position,
+                };
+            }
+        }
+
+        Self {
+            name: raw.clone(),
+            inline: None,
+            raw,
+            position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    #[test]
+    fn help() {
+        let mut parser = Parser::new()
+            .with_usage("")
+            .with_author("me")
+            .with_description("Example program")
+            .exit_on_help(false);
+
+        parser.add("--say", 0, "Repeat something");
+        let expand = parser.add_template(
+            Template::new()
+                .matches("-x")
+                .matches("--expand")
+                .optional_values(false)
+                .number_of_values(0)
+                .with_help("Expand something."),
+        );
+
+        let sub_sub = parser
+            .add_subcommand_template(
+                expand,
+                Template::new()
+                    .matches("--string")
+                    .number_of_values(0)
+                    .with_help("Expands a string"),
+            )
+            .unwrap();
+
+        let sub_sub_sub = parser
+            .add_subcommand(sub_sub, "--super-test", 0, "Amazing super test.")
+            .unwrap();
+
+        let _inf = parser
+            .add_subcommand(sub_sub_sub, "-i", 0, "Infinite nesting!")
+            .unwrap();
+
+        match parser.parse(Some(vec!["--help"])) {
+            Err(HpError::HelpRequested(help)) => {
+                println!("{help}");
+                assert!(help.contains("Author: me"));
+                assert!(help.contains("Infinite nesting!"));
+            }
+            _ => panic!("expected the help message"),
+        }
+    }
+
+    #[test]
+    fn parsed_args() {
+        let mut parser = Parser::new();
+        parser.add("--hello", 0, "hello");
+        let arg = parser.add("arg", 3, "hello");
+        parser.add_template(
+            Template::new()
+                .matches("-not-found")
+                .number_of_values(0)
+                .with_help("bad.")
+                .on_parse(|_| ()),
+        );
+
+        let result = parser.parse(Some(vec!["--hello", "arg", "h", "w", "x"]));
+
+        assert!(result.is_ok());
+        let r = result.expect("bad");
+
+        assert!(r.has("--hello"));
+        assert!(r.get_with_id(arg).unwrap().values.len() == 3);
+        assert!(!r.has("-not-found"))
+    }
+
+    #[test]
+    fn context_parsing() {
+        let mut parser = Parser::new()
+            .with_usage("")
+            .with_author("me")
+            .with_description("Example program")
+            .exit_on_help(true);
+
+        parser.add("--say", 0, "Repeat something");
+        let expand = parser.add_template(
+            Template::new()
+                .matches("-x")
+                .matches("--expand")
+                .optional_values(false)
+                .number_of_values(0)
+                .with_help("Expand something."),
+        );
+
+        let sub_sub = parser
+            .add_subcommand_template(
+                expand,
+                Template::new()
+                    .matches("--string")
+                    .number_of_values(0)
+                    .with_help("Expands a string"),
+            )
+            .unwrap();
+
+        let sub_sub_sub = parser
+            .add_subcommand(sub_sub, "--super-test", 0, "Amazing super test.")
+            .unwrap();
+
+        parser
+            .add_subcommand(sub_sub_sub, "-i", 0, "Infinite nesting!")
+            .unwrap();
+
+        let result = parser
+            .parse(Some(vec!["-x", "--string", "--super-test", "-i"]))
+            .unwrap();
+
+        assert!(result.has_with_context(expand, "--string"));
+        assert!(result.has_with_context(sub_sub, "--super-test"));
+        assert!(result.has_with_context(sub_sub_sub, "-i"));
+    }
+
+    #[test]
+    fn has_path() {
+        let mut parser = Parser::new();
+        let expand = parser.add_template(Template::new().matches("-x").matches("--expand"));
+        let string = parser
+            .add_subcommand(expand, "--string", 0, "String.")
+            .unwrap();
+        let test = parser
+            .add_subcommand(string, "--super-test", 0, "Test.")
+            .unwrap();
+        parser.add_subcommand(test, "-i", 0, "Infinite.").unwrap();
+
+        let result = parser
+            .parse(Some(vec!["-x", "--string", "--super-test", "-i"]))
+            .unwrap();
+        assert!(result.has_path(&["-x", "--string", "--super-test", "-i"]));
+        assert!(result.has_path(&["-x", "--string"]));
+        assert!(!result.has_path(&["-x", "--super-test"]));
+        assert!(!result.has_path(&["--string"]));
+    }
+
+    #[test]
+    fn out_of_context() {
+        let mut parser: Parser = Parser::new()
+            .with_usage("")
+            .with_author("me")
+            .with_description("Example program")
+            .exit_on_help(true);
+
+        parser.add("--say", 0, "Repeat something");
+        let expand = parser.add_template(
+            Template::new()
+                .matches("-x")
+                .matches("--expand")
+                .optional_values(false)
+                .number_of_values(0)
+                .with_help("Expand something."),
+        );
+
+        parser
+            .add_subcommand_template(
+                expand,
+                Template::new()
+                    .matches("--string")
+                    .number_of_values(0)
+                    .with_help("Expands a string"),
+            )
+            .unwrap();
+
+        let result = parser.parse(Some(vec!["--string"]));
+
+        assert!(result.is_err());
+
+        println!("{}", result.err().unwrap())
+    }
+
+    #[test]
+    fn out_of_context_parent() {
+        for _ in 0..16 {
+            let mut parser = Parser::new();
+            let db = parser.add("db", 0, "Database.");
+            let cache = parser.add("cache", 0, "Cache.");
+            parser.add_subcommand(db, "--url", 1, "URL.").unwrap();
+            parser.add_subcommand(cache, "--url", 1, "URL.").unwrap();
+
+            assert_eq!(
+                parser.parse(Some(vec!["--url", "x"])).unwrap_err(),
+                HpError::OutOfContext("--url".into(), "db".into(), 0)
+            );
+        }
+    }
+
+    #[test]
+    fn action() {
+        let mut parser = Parser::new();
+        let mut last_val = String::new();
+
+        parser.add_template(
+            Template::new()
+                .matches("say")
+                .on_parse(move |values| {
+                    println!("Saying: ");
+                    for each in values.iter() {
+                        last_val = each.to_string().clone();
+                    }
+
+                    println!("Last val {last_val}");
+                })
+                .number_of_values(8)
+                .optional_values(true),
+        );
+
+        assert!(parser.parse(Some(vec!["say", "hello", "world"])).is_ok())
+    }
+
+    #[test]
+    fn inline_values() {
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say something.");
+        let env = parser.add("--env", 1, "Set a variable.");
+        let pair = parser.add("--pair", 2, "Take two values.");
+        parser.add("--flag", 0, "A flag.");
+
+        let result = parser
+            .parse(Some(vec!["--say=hello", "--env=FOO=bar", "--pair=a", "b"]))
+            .unwrap();
+
+        assert_eq!(result.get_with_id(say).unwrap().values(), &vec!["hello"]);
+        assert_eq!(result.get_with_id(env).unwrap().values(), &vec!["FOO=bar"]);
+        assert_eq!(result.get_with_id(pair).unwrap().values(), &vec!["a", "b"]);
+
+        assert_eq!(
+            parser.parse(Some(vec!["--flag=value"])).unwrap_err(),
+            HpError::NumberOfValues("--flag".into(), 1, 0, 0)
+        );
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut parser = Parser::new();
+        let add = parser.add("--add", 2, "Add two numbers.");
+
+        let result = parser.parse(Some(vec!["--add", "2", "x"])).unwrap();
+        let add = result.get_with_id(add).unwrap();
+
+        assert_eq!(add.value_as::<i32>(0), Ok(2));
+        assert_eq!(
+            add.value_as::<i32>(1),
+            Err(HpError::ParseFailed("x".into(), "i32".into()))
+        );
+        assert_eq!(add.value_as::<i32>(2), Err(HpError::IndexOutOfRange(2, 2)));
+        assert!(add.values_as::<i32>().is_err());
+        assert_eq!(add.values_as::<String>().unwrap(), vec!["2", "x"]);
+    }
+
+    #[test]
+    fn strict() {
+        let mut parser = Parser::new().strict(true);
+        parser.add("--verbose", 0, "Print more information.");
+        parser.add("--say", 1, "Say something.");
+
+        assert!(parser
+            .parse(Some(vec!["--verbose", "word", "--say", "-x"]))
+            .is_ok());
+        assert_eq!(
+            parser.parse(Some(vec!["--vebose"])).unwrap_err(),
+            HpError::UnknownArgument("--vebose".into(), Some("--verbose".into()), 0)
+        );
+        assert_eq!(
+            parser.parse(Some(vec!["--nothing-like-it"])).unwrap_err(),
+            HpError::UnknownArgument("--nothing-like-it".into(), None, 0)
+        );
+
+        let mut lenient = Parser::new();
+        lenient.add("--verbose", 0, "Print more information.");
+        assert!(lenient.parse(Some(vec!["--vebose"])).is_ok());
+    }
+
+    #[test]
+    fn count() {
+        let mut parser = Parser::new();
+        let verbose = parser.add_template(Template::new().matches("-v").matches("--verbose"));
+        parser.add("--quiet", 0, "Print less information.");
+
+        let result = parser.parse(Some(vec!["-v", "-v", "--verbose"])).unwrap();
+
+        assert_eq!(result.count("-v"), 3);
+        assert_eq!(result.count("--verbose"), 3);
+        assert_eq!(result.count_with_id(verbose), 3);
+        assert_eq!(result.count("--quiet"), 0);
+    }
+
+    #[test]
+    fn bundled_flags() {
+        let mut parser = Parser::new();
+        let a = parser.add("-a", 0, "A flag.");
+        let b = parser.add("-b", 0, "B flag.");
+        parser.add("-c", 1, "Takes a value.");
+
+        let result = parser.parse(Some(vec!["-ab", "-ba"])).unwrap();
+        assert_eq!(result.count_with_id(a), 2);
+        assert_eq!(result.count_with_id(b), 2);
+
+        let result = parser.parse(Some(vec!["-ac", "--ab"])).unwrap();
+        assert!(!result.has_with_id(a));
+
+        let mut strict = Parser::new().strict(true);
+        strict.add("-a", 0, "A flag.");
+        assert_eq!(
+            strict.parse(Some(vec!["-ax"])).unwrap_err(),
+            HpError::UnknownArgument("-ax".into(), Some("-a".into()), 0)
+        );
+    }
+
+    #[test]
+    fn trailing() {
+        let mut parser = Parser::new();
+        let file = parser.add("--file", 1, "Input file.");
+        let flag = parser.add("--flag", 0, "A flag.");
+
+        let result = parser
+            .parse(Some(vec!["--file", "-", "--", "--flag", "x"]))
+            .unwrap();
+
+        assert_eq!(result.get_with_id(file).unwrap().values(), &vec!["-"]);
+        assert!(!result.has_with_id(flag));
+        assert_eq!(result.trailing(), &vec!["--flag", "x"]);
+
+        let result = parser.parse(Some(vec!["--flag"])).unwrap();
+        assert!(result.trailing().is_empty());
+    }
+
+    #[test]
+    fn default_values() {
+        let mut parser = Parser::new();
+        let color = parser.add_template(
+            Template::new()
+                .matches("--color")
+                .number_of_values(1)
+                .optional_values(true)
+                .default_value("auto"),
+        );
+        let pair = parser.add_template(
+            Template::new()
+                .matches("--pair")
+                .number_of_values(2)
+                .optional_values(true)
+                .default_values(vec!["a".into(), "b".into()]),
+        );
+
+        let result = parser.parse(Some(vec!["--color", "--pair"])).unwrap();
+        assert_eq!(result.get_with_id(color).unwrap().values(), &vec!["auto"]);
+        assert_eq!(result.get_with_id(pair).unwrap().values(), &vec!["a", "b"]);
+
+        let result = parser.parse(Some(vec!["--color", "never"])).unwrap();
+        assert_eq!(result.get_with_id(color).unwrap().values(), &vec!["never"]);
+        assert!(!result.has_with_id(pair));
+    }
+
+    #[test]
+    fn required() {
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("-o")
+                .matches("--output")
+                .number_of_values(1)
+                .required(true),
+        );
+        let db = parser.add("db", 0, "Database commands.");
+        parser
+            .add_subcommand_template(
+                db,
+                Template::new()
+                    .matches("--url")
+                    .number_of_values(1)
+                    .required(true),
+            )
+            .unwrap();
+        parser.add_template(Template::new().matches("--input").required(true));
+
+        assert_eq!(
+            parser.parse(Some(vec![])).unwrap_err(),
+            HpError::MissingRequired(vec!["-o".into(), "--input".into()])
+        );
+        assert_eq!(
+            parser
+                .parse(Some(vec!["--input", "db", "--output", "x"]))
+                .unwrap_err(),
+            HpError::MissingRequired(vec!["--url".into()])
+        );
+        assert!(parser.parse(Some(vec!["--input", "-o", "x"])).is_ok());
+    }
+
+    #[test]
+    fn version() {
+        let mut parser = Parser::new()
+            .with_program_name("myprog")
+            .with_version("1.0.0")
+            .exit_on_version(false)
+            .strict(true);
+        let say = parser.add("--say", 0, "Say something.");
+
+        assert_eq!(
+            parser.parse(Some(vec!["--say", "--version"])).unwrap_err(),
+            HpError::VersionRequested("myprog 1.0.0".into())
+        );
+        assert!(parser.parse(Some(vec!["--say"])).unwrap().has_with_id(say));
+
+        let unversioned = Parser::new().strict(true);
+        assert_eq!(
+            unversioned.parse(Some(vec!["-V"])).unwrap_err(),
+            HpError::UnknownArgument("-V".into(), None, 0)
+        );
+    }
+
+    #[test]
+    fn shared_parse() {
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say something.");
+
+        let shared = Rc::new(parser);
+        let first = shared.parse(Some(vec!["--say", "a"])).unwrap();
+        let second = Rc::clone(&shared).parse(Some(vec!["--say", "b"])).unwrap();
+
+        assert_eq!(first.get_with_id(say).unwrap().values(), &vec!["a"]);
+        assert_eq!(second.get_with_id(say).unwrap().values(), &vec!["b"]);
+    }
+
+    #[test]
+    fn repeated_parse() {
+        let fired = Arc::new(Mutex::new(0));
+        let counter = fired.clone();
+        let mut parser = Parser::new().capture_unknown(true);
+        let add = parser.add_template(
+            Template::new()
+                .matches("--add")
+                .number_of_values(1)
+                .fold(0, |sum, values| sum + values[0].parse::<i32>().unwrap_or(0))
+                .on_parse(move |_| *counter.lock().unwrap() += 1),
+        );
+        parser.command("run", "Run.").add("-q", 0, "Quiet.");
+
+        let first = parser
+            .parse(Some(vec!["run", "--add", "1", "--add", "2", "-x", "file"]))
+            .unwrap();
+        let second = parser.parse(Some(vec!["--add", "5"])).unwrap();
+
+        assert_eq!(first.folded::<i32>(add), Some(3));
+        assert_eq!(first.subcommand(), Some("run"));
+        assert_eq!(first.positionals(), &vec!["file"]);
+        assert_eq!(second.folded::<i32>(add), Some(5));
+        assert_eq!(second.count("--add"), 1);
+        assert_eq!(second.subcommand(), None);
+        assert!(second.positionals().is_empty() && second.unknown().is_empty());
+        assert_eq!(*fired.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn possible_values() {
+        let choices = vec!["always".to_string(), "auto".into(), "never".into()];
+        let mut parser = Parser::new().exit_on_help(false);
+        parser.add_template(
+            Template::new()
+                .matches("--color")
+                .number_of_values(1)
+                .possible_values(choices.clone()),
+        );
+        parser.add_template(
+            Template::new()
+                .matches("--when")
+                .number_of_values(1)
+                .possible_values(choices.clone())
+                .ignore_case(true),
+        );
+
+        assert!(parser
+            .parse(Some(vec!["--color", "auto", "--when", "NEVER"]))
+            .is_ok());
+        assert_eq!(
+            parser.parse(Some(vec!["--color", "Auto"])).unwrap_err(),
+            HpError::InvalidChoice("--color".into(), "Auto".into(), choices)
+        );
+
+        let help = parser.parse(Some(vec!["--help"])).unwrap_err().to_string();
+        assert!(help.contains("--color [1 value/s] {always|auto|never}"));
+    }
+
+    #[test]
+    fn env_fallback() {
+        env::set_var("HP_TEST_ENV_TOKEN", "secret");
+        env::set_var("HP_TEST_ENV_PAIR", "a  b");
+
+        let mut parser = Parser::new();
+        let token = parser.add_template(
+            Template::new()
+                .matches("--token")
+                .number_of_values(1)
+                .required(true)
+                .env("HP_TEST_ENV_TOKEN"),
+        );
+        let pair = parser.add_template(
+            Template::new()
+                .matches("--pair")
+                .number_of_values(2)
+                .env("HP_TEST_ENV_PAIR"),
+        );
+        let unset = parser.add_template(
+            Template::new()
+                .matches("--unset")
+                .number_of_values(1)
+                .env("HP_TEST_ENV_UNSET"),
+        );
+
+        let result = parser.parse(Some(vec![])).unwrap();
+        assert_eq!(result.get("--token").unwrap().values(), &vec!["secret"]);
+        assert_eq!(result.get_with_id(pair).unwrap().values(), &vec!["a", "b"]);
+        assert!(!result.has_with_id(unset));
+
+        let result = parser.parse(Some(vec!["--token", "explicit"])).unwrap();
+        assert_eq!(
+            result.get_with_id(token).unwrap().values(),
+            &vec!["explicit"]
+        );
+
+        env::set_var("HP_TEST_ENV_SHORT", "one");
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--pair")
+                .number_of_values(2)
+                .env("HP_TEST_ENV_SHORT"),
+        );
+        let err = parser.parse(Some(vec![])).unwrap_err();
+        assert_eq!(
+            err,
+            HpError::NumberOfValues("--pair".into(), 1, 2, usize::MAX)
+        );
+        assert_eq!(err.position(), None);
+        assert_eq!(
+            err.to_string_plain(),
+            "ERROR: In argument '--pair', expected '2' value/s, received '1'."
+        );
+
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--range")
+                .values_range(2, 3)
+                .env("HP_TEST_ENV_SHORT"),
+        );
+        assert_eq!(
+            parser.parse(Some(vec![])).unwrap_err(),
+            HpError::NumberOfValuesRange("--range".into(), 1, 2, 3, usize::MAX)
+        );
+    }
+
+    #[test]
+    fn remove() {
+        let mut parser = Parser::new().strict(true);
+        let quiet = parser.add_template(Template::new().matches("-q").matches("--quiet"));
+        let db = parser.add("db", 0, "Database commands.");
+        let url = parser
+            .add_subcommand(db, "--url", 1, "Database URL.")
+            .unwrap();
+        parser
+            .add_subcommand(url, "--check", 0, "Check the URL.")
+            .unwrap();
+
+        assert!(parser.remove("--quiet"));
+        assert!(!parser.remove("--quiet"));
+        assert!(parser.parse(Some(vec!["-q"])).is_err());
+        assert!(!parser.order.contains(&quiet));
+
+        assert!(!parser.remove_with_id(db));
+        assert!(parser.remove_recursive(db));
+        assert!(parser.stored.is_empty());
+        assert!(parser.order.is_empty());
+        assert!(!parser.remove_recursive(db));
+    }
+
+    #[test]
+    fn iterate() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database commands.");
+        parser
+            .add_subcommand(db, "--url", 1, "Database URL.")
+            .unwrap();
+        parser.add("--verbose", 0, "Print more information.");
+
+        let result = parser.parse(Some(vec![])).unwrap();
+        assert!(result.is_empty());
+
+        let result = parser
+            .parse(Some(vec!["db", "--url", "x", "--verbose"]))
+            .unwrap();
+        let mut names: Vec<&str> = result.iter().map(|(name, _)| name).collect();
+        names.sort();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(names, vec!["--url", "--verbose", "db"]);
+
+        let mut parser = Parser::new();
+        parser.add_template(Template::new().matches("-v").matches("--verbose"));
+        let result = parser.parse(Some(vec!["-v", "--verbose"])).unwrap();
+        let names: Vec<&str> = result.iter().map(|(name, _)| name).collect();
+        assert_eq!(result.len(), 1);
+        assert_eq!(names, vec!["--verbose"]);
+    }
+
+    #[test]
+    fn json() {
+        let mut parser = Parser::new();
+        parser.add("--say", 2, "Say something.");
+        parser.add("--flag", 0, "A flag.");
+
+        let result = parser
+            .parse(Some(vec!["--say", "\"hi\"", "a\\b", "--flag"]))
+            .unwrap();
+        assert_eq!(
+            result.to_json(),
+            r#"{"--flag":[],"--say":["\"hi\"","a\\b"]}"#
+        );
+        assert_eq!(result.get("--flag").unwrap().to_json(), "[]");
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let mut parser = Parser::new();
+        let one = parser.add("-1", 0, "Once.");
+        let two = parser.add("-2", 0, "Twice.");
+        let add = parser.add_template(
+            Template::new()
+                .matches("--add")
+                .number_of_values(2)
+                .allow_negative_numbers(true),
+        );
+        let sub = parser.add_template(
+            Template::new()
+                .matches("--sub")
+                .number_of_values(2)
+                .optional_values(true),
+        );
+
+        let result = parser
+            .parse(Some(vec!["--add", "-12", "-0.5", "-1"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(add).unwrap().values(),
+            &vec!["-12", "-0.5"]
+        );
+        assert!(result.has_with_id(one));
+
+        let result = parser.parse(Some(vec!["--add", "-1", "-2"])).unwrap_err();
+        assert_eq!(result, HpError::ValueIsFlag("--add".into(), "-1".into(), 0));
+
+        let result = parser.parse(Some(vec!["--sub", "-12", "-7"])).unwrap();
+        assert!(result.get_with_id(sub).unwrap().values().is_empty());
+        assert!(result.has_with_id(one) && result.has_with_id(two));
+
+        assert!(is_negative_number("-5"));
+        assert!(!is_negative_number("-"));
+        assert!(!is_negative_number("-1.2.3"));
+        assert!(!is_negative_number("--5"));
+    }
+
+    #[test]
+    fn help_flags() {
+        let mut parser = Parser::new()
+            .exit_on_help(false)
+            .help_flags(vec!["--help".into(), "-?".into()]);
+        let host = parser.add("-h", 1, "Host to connect to.");
+
+        let result = parser.parse(Some(vec!["-h", "localhost"])).unwrap();
+        assert_eq!(
+            result.get_with_id(host).unwrap().values(),
+            &vec!["localhost"]
+        );
+
+        let help = parser.parse(Some(vec!["-?"])).unwrap_err().to_string();
+        assert!(help.ends_with("--help, -?         Print this help message!"));
+
+        let mut parser = Parser::new().exit_on_help(false).exit_on_version(false);
+        parser.add("--say", 1, "Say something.");
+        parser.add_template(Template::new().matches("--all").variadic(true).greedy(true));
+        parser.add_template(
+            Template::new()
+                .matches("--raw")
+                .number_of_values(1)
+                .accept_dash_values(true),
+        );
+        for args in [
+            vec!["--say", "--help"],
+            vec!["--all", "x", "-h"],
+            vec!["--raw", "-h"],
+        ] {
+            assert!(matches!(
+                parser.parse(Some(args)),
+                Err(HpError::HelpRequested(_))
+            ));
+        }
+        let parser = parser.with_version("1.0");
+        assert!(matches!(
+            parser.parse(Some(vec!["--say", "-V"])),
+            Err(HpError::VersionRequested(_))
+        ));
+
+        let mut manual = Parser::new().auto_help(false);
+        let help = manual.add("--help", 0, "Custom help.");
+        assert!(manual
+            .parse(Some(vec!["--help"]))
+            .unwrap()
+            .has_with_id(help));
+    }
+
+    #[test]
+    fn help_alignment() {
+        let mut parser = Parser::new().exit_on_help(false);
+        let a = parser.add("-a", 0, "First level.");
+        let b = parser.add_subcommand(a, "-b", 0, "Second level.").unwrap();
+        let c = parser.add_subcommand(b, "-c", 0, "Third level.").unwrap();
+        parser
+            .add_subcommand(
+                c,
+                "--a-very-long-deeply-nested-argument",
+                3,
+                "Fourth level.",
+            )
+            .unwrap();
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        let columns: Vec<usize> = help
+            .lines()
+            .skip_while(|line| *line != "Arguments:")
+            .skip(1)
+            .map(|line| {
+                ["First", "Second", "Third", "Fourth", "Print"]
+                    .iter()
+                    .find_map(|word| line.find(word))
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(columns.len(), 5);
+        assert!(columns.iter().all(|c| *c == columns[0]));
+    }
+
+    #[test]
+    fn help_aliases() {
+        let mut parser = Parser::new().exit_on_help(false);
+        let expand = parser.add_template(
+            Template::new()
+                .matches("-x")
+                .matches("--expand")
+                .with_help("Expand something."),
+        );
+        parser
+            .add_subcommand(expand, "--first", 0, "First subcommand.")
+            .unwrap();
+        parser
+            .add_subcommand(expand, "--second", 0, "Second subcommand.")
+            .unwrap();
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        assert_eq!(help.matches("Expand something.").count(), 1);
+        assert!(help.contains("-x | --expand"));
+
+        let first = help.find("First subcommand.").unwrap();
+        let second = help.find("Second subcommand.").unwrap();
+        assert!(help.find("Expand something.").unwrap() < first && first < second);
+    }
+
+    #[test]
+    fn validate() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database commands.");
+        parser
+            .add_subcommand(db, "--url", 1, "Database URL.")
+            .unwrap();
+        parser.add("--url", 1, "Another URL, in a different context.");
+        parser
+            .add_subcommand(db, "--name", 1, "Database name.")
+            .unwrap();
+        assert_eq!(
+            parser.validate(),
+            Ok(vec![
+                "Subcommand '--url' of 'db' shadows the top level argument '--url'.".into()
+            ])
+        );
+
+        parser.add_template(Template::new().matches("-d").matches("db"));
+        assert_eq!(
+            parser.parse(Some(vec![])).unwrap_err(),
+            HpError::DuplicateMatch("db".into())
+        );
+
+        let mut orphan = Parser::new();
+        assert_eq!(
+            orphan.add_subcommand(42, "--url", 1, "Database URL."),
+            Err(HpError::UnknownParent(42))
+        );
+        let invalid = orphan.add("a b", 0, "An invalid name.");
+        assert_eq!(
+            orphan.add_subcommand(invalid, "--url", 1, "Database URL."),
+            Err(HpError::UnknownParent(invalid))
+        );
+        let mut orphan = Parser::new();
+        let mut template = Template::new().matches("--url");
+        template.subarg(42);
+        orphan.add_template(template);
+        assert_eq!(orphan.validate(), Err(HpError::UnknownParent(42)));
+
+        let mut lints = Parser::new();
+        lints.add_template(Template::new().matches("-x").optional_values(true));
+        lints.add_template(
+            Template::new()
+                .matches("--color")
+                .number_of_values(1)
+                .optional_values(true)
+                .required(true),
+        );
+        assert_eq!(
+            lints.validate(),
+            Ok(vec![
+                "'-x' has optional values, but it takes no values.".into(),
+                "'--color' is required, but its values are optional.".into()
+            ])
+        );
+    }
+
+    #[test]
+    fn groups() {
+        let mut parser = Parser::new();
+        let json = parser.add("--json", 0, "Output JSON.");
+        let yaml = parser.add("--yaml", 0, "Output YAML.");
+        let toml = parser.add("--toml", 0, "Output TOML.");
+        parser.exclusive_group(vec![json, yaml, toml]);
+        parser.required_group(vec![json, yaml, toml]);
+
+        assert!(parser.parse(Some(vec!["--yaml"])).is_ok());
+        assert_eq!(
+            parser.parse(Some(vec!["--toml", "--json"])).unwrap_err(),
+            HpError::ConflictingArguments(vec!["--json".into(), "--toml".into()])
+        );
+        assert_eq!(
+            parser.parse(Some(vec![])).unwrap_err(),
+            HpError::MissingOneOf(vec!["--json".into(), "--yaml".into(), "--toml".into()])
+        );
+    }
+
+    #[test]
+    fn global() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database commands.");
+        let migrate = parser
+            .add_subcommand(db, "migrate", 0, "Run the migrations.")
+            .unwrap();
+        let dry = parser
+            .add_subcommand_template(
+                db,
+                Template::new()
+                    .matches("--dry-run")
+                    .with_help("Do nothing.")
+                    .global(true),
+            )
+            .unwrap();
+        let local = parser
+            .add_subcommand(migrate, "--local", 0, "Local only.")
+            .unwrap();
+        parser
+            .add_subcommand(local, "--dry-run", 1, "A more specific dry run.")
+            .unwrap();
+
+        let result = parser
+            .parse(Some(vec!["db", "migrate", "--dry-run"]))
+            .unwrap();
+        assert!(result.has_with_context(db, "--dry-run"));
+        assert!(result.has_with_id(dry));
+
+        let result = parser
+            .parse(Some(vec!["db", "migrate", "--local", "--dry-run", "x"]))
+            .unwrap();
+        assert!(!result.has_with_id(dry));
+        assert!(result.has_with_context(local, "--dry-run"));
+    }
+
+    #[test]
+    fn variadic() {
+        let mut parser = Parser::new().exit_on_help(false);
+        let add = parser.add_template(Template::new().matches("add").variadic(true));
+        let list = parser.add_template(
+            Template::new()
+                .matches("--list")
+                .variadic(true)
+                .optional_values(true),
+        );
+
+        let result = parser
+            .parse(Some(vec!["add", "1", "2", "3", "--list", "--", "x"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(add).unwrap().values(),
+            &vec!["1", "2", "3"]
+        );
+        assert!(result.get_with_id(list).unwrap().values().is_empty());
+        assert_eq!(result.trailing(), &vec!["x"]);
+
+        assert_eq!(
+            parser.parse(Some(vec!["add", "--list"])).unwrap_err(),
+            HpError::ValueIsFlag("add".into(), "--list".into(), 0)
+        );
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        assert!(help.contains("add [value/s...]"));
+        assert!(help.contains("--list [optional value/s...]"));
+    }
+
+    #[test]
+    fn positionals() {
+        let mut parser = Parser::new();
+        parser.add("build", 0, "Build the files.");
+        parser.add("--out", 1, "Output directory.");
+
+        let result = parser
+            .parse(Some(vec![
+                "build",
+                "a.rs",
+                "--out",
+                "dir",
+                "b.rs",
+                "--unknown",
+                "-",
+                "--",
+                "c.rs",
+            ]))
+            .unwrap();
+        assert_eq!(result.positionals(), &vec!["a.rs", "b.rs", "-"]);
+        assert_eq!(result.trailing(), &vec!["c.rs"]);
+    }
+
+    #[test]
+    fn get_or() {
+        let mut parser = Parser::new();
+        parser.add("--name", 1, "Your name.");
+        parser.add_template(
+            Template::new()
+                .matches("--tags")
+                .variadic(true)
+                .optional_values(true),
+        );
+
+        let result = parser.parse(Some(vec!["--tags"])).unwrap();
+        assert_eq!(result.get_or("--name", "world"), "world");
+        assert_eq!(result.get_all_or("--tags", vec!["all".into()]), vec!["all"]);
+
+        let result = parser
+            .parse(Some(vec!["--name", "me", "--tags", "a", "b"]))
+            .unwrap();
+        assert_eq!(result.get_or("--name", "world"), "me");
+        assert_eq!(result.get_all_or("--tags", vec![]), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn response_files() {
+        let dir = env::temp_dir();
+        let args = dir.join("hp_test_response_args.txt");
+        let nested = dir.join("hp_test_response_nested.txt");
+        std::fs::write(&args, "--say hello\n--flag").unwrap();
+        std::fs::write(&nested, "--say @other.txt").unwrap();
+        let args = format!("@{}", args.display());
+        let nested = format!("@{}", nested.display());
+
+        let mut parser = Parser::new().response_files(true);
+        let say = parser.add("--say", 1, "Say something.");
+        let flag = parser.add("--flag", 0, "A flag.");
+
+        let result = parser.parse(Some(vec![&args, "--", &args])).unwrap();
+        assert_eq!(result.get_with_id(say).unwrap().values(), &vec!["hello"]);
+        assert!(result.has_with_id(flag));
+        assert_eq!(result.trailing(), &vec![args.as_str()]);
+
+        assert!(matches!(
+            parser.parse(Some(vec![&nested])),
+            Err(HpError::ResponseFileError(..))
+        ));
+        assert!(matches!(
+            parser.parse(Some(vec!["@/this/file/does/not/exist"])),
+            Err(HpError::ResponseFileError(..))
+        ));
+
+        let lenient = Parser::new();
+        assert_eq!(
+            lenient.parse(Some(vec![&args])).unwrap().positionals(),
+            &vec![args.as_str()]
+        );
+    }
+
+    #[test]
+    fn abbreviations() {
+        let mut parser = Parser::new().allow_abbreviations(true);
+        let verbose = parser.add("--verbose", 0, "Print more information.");
+        parser.add("--version", 0, "Print the version.");
+        let ver = parser.add("--ver", 1, "Exact match.");
+
+        let result = parser.parse(Some(vec!["--verb", "--ver", "x"])).unwrap();
+        assert!(result.has_with_id(verbose));
+        assert!(result.has_with_id(ver));
+
+        assert_eq!(
+            parser.parse(Some(vec!["--ve"])).unwrap_err(),
+            HpError::AmbiguousArgument(
+                "--ve".into(),
+                vec!["--ver".into(), "--verbose".into(), "--version".into()]
+            )
+        );
+
+        let exact = Parser::new();
+        assert!(exact.parse(Some(vec!["--verb"])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn templates() {
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say something.");
+        let count = parser.add_template(
+            Template::new()
+                .matches("-c")
+                .matches("--count")
+                .with_help("Count."),
+        );
+        let add = parser
+            .add_subcommand_template(
+                count,
+                Template::new()
+                    .matches("--add")
+                    .number_of_values(2)
+                    .optional_values(true),
+            )
+            .unwrap();
+
+        let infos = parser.templates();
+        assert_eq!(
+            infos.iter().map(|i| i.id).collect::<Vec<_>>(),
+            vec![say, count, add]
+        );
+        assert_eq!(
+            infos[2],
+            TemplateInfo {
+                matches: vec!["--add".into()],
+                num_values: 2,
+                optional_vals: true,
+                help: "".into(),
+                subargument_of: Some(count),
+                id: add,
+            }
+        );
+        assert_eq!(infos[1].matches, vec!["-c", "--count"]);
+    }
+
+    #[test]
+    fn values_range() {
+        let mut parser = Parser::new().exit_on_help(false);
+        let files = parser.add_template(Template::new().matches("--files").values_range(1, 3));
+        parser.add_template(
+            Template::new()
+                .matches("--maybe")
+                .values_range(1, 2)
+                .optional_values(true),
+        );
+
+        let result = parser
+            .parse(Some(vec!["--files", "a", "b", "c", "d"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(files).unwrap().values(),
+            &vec!["a", "b", "c"]
+        );
+        assert_eq!(result.positionals(), &vec!["d"]);
+
+        assert_eq!(
+            parser.parse(Some(vec!["--files"])).unwrap_err(),
+            HpError::NumberOfValuesRange("--files".into(), 0, 1, 3, 0)
+        );
+        assert!(parser.parse(Some(vec!["--maybe"])).is_ok());
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        assert!(help.contains("--files [1-3 value/s]"));
+    }
+
+    #[test]
+    fn int_value() {
+        let arg = ParsedArgument::new(
+            0,
+            vec!["0xFF", "0o17", "0b101", "-0x10", "42", "0xZZ", "0x-1"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        );
+
+        assert_eq!(arg.int_value(0), Ok(255));
+        assert_eq!(arg.int_value(1), Ok(15));
+        assert_eq!(arg.int_value(2), Ok(5));
+        assert_eq!(arg.int_value(3), Ok(-16));
+        assert_eq!(arg.int_value(4), Ok(42));
+        assert_eq!(
+            arg.int_value(5),
+            Err(HpError::InvalidInteger("0xZZ".into()))
+        );
+        assert_eq!(
+            arg.int_value(6),
+            Err(HpError::InvalidInteger("0x-1".into()))
+        );
+        assert_eq!(arg.int_value(7), Err(HpError::IndexOutOfRange(7, 7)));
+    }
+
+    #[test]
+    fn action_context() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        let mut parser = Parser::new().allow_abbreviations(true);
+        let verbose = parser.add_template(
+            Template::new()
+                .matches("-v")
+                .matches("--verbose")
+                .on_parse_ctx(move |name, id, _| log.lock().unwrap().push((name.to_string(), id))),
+        );
+
+        parser.parse(Some(vec!["-v"])).unwrap();
+        parser.parse(Some(vec!["--verb"])).unwrap();
+        assert_eq!(
+            *fired.lock().unwrap(),
+            vec![
+                ("-v".to_string(), verbose),
+                ("--verbose".to_string(), verbose)
+            ]
+        );
+    }
+
+    #[test]
+    fn action_try() {
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--port")
+                .number_of_values(1)
+                .on_parse_try(|values| match values[0].parse::<u16>() {
+                    Ok(port) if port >= 1024 => Ok(()),
+                    _ => Err("expected a port between 1024 and 65535".into()),
+                }),
+        );
+
+        assert!(parser.parse(Some(vec!["--port", "8080"])).is_ok());
+        assert_eq!(
+            parser.parse(Some(vec!["--port", "80"])).unwrap_err(),
+            HpError::ActionFailed(
+                "--port".into(),
+                "expected a port between 1024 and 65535".into()
+            )
+        );
+    }
+
+    #[test]
+    fn validate_each() {
+        let fired = Arc::new(Mutex::new(0));
+        let counter = fired.clone();
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--host")
+                .variadic(true)
+                .validate_each(|value| match value.parse::<std::net::IpAddr>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err("expected an IP address".into()),
+                })
+                .on_parse_try(move |values| {
+                    *counter.lock().unwrap() += 1;
+                    match values.len() {
+                        3 => Err("too many hosts".into()),
+                        _ => Ok(()),
+                    }
+                }),
+        );
+
+        assert!(parser
+            .parse(Some(vec!["--host", "::1", "10.0.0.1"]))
+            .is_ok());
+        assert_eq!(
+            parser
+                .parse(Some(vec!["--host", "::1", "localhost", "x"]))
+                .unwrap_err(),
+            HpError::ValueValidationFailed(
+                "--host".into(),
+                "localhost".into(),
+                "expected an IP address".into()
+            )
+        );
+        assert_eq!(*fired.lock().unwrap(), 1);
+        assert!(matches!(
+            parser.parse(Some(vec!["--host", "::1", "::2", "::3"])),
+            Err(HpError::ActionFailed(..))
+        ));
+    }
+
+    #[test]
+    fn synopsis() {
+        let mut parser = Parser::new().with_program_name("myprog");
+        parser.add("--say", 1, "Say something.");
+        parser.add("-x", 0, "A flag.");
+        let c = parser.add("-c", 0, "A command.");
+        parser
+            .add_subcommand(c, "add", 2, "Add two numbers.")
+            .unwrap();
+        parser.add_template(
+            Template::new()
+                .matches("--name")
+                .number_of_values(1)
+                .required(true),
+        );
+        parser.add_template(Template::new().matches("--files").variadic(true));
+
+        assert_eq!(
+            parser.synopsis(),
+            "myprog [--say <value>] [-x] [-c <subcommand>] --name <value> [--files <value>...]"
+        );
+    }
+
+    #[test]
+    fn help_groups() {
+        let mut parser = Parser::new().exit_on_help(false);
+        parser.add_template(Template::new().matches("--out").group("Output options"));
+        parser.add_template(Template::new().matches("--in").group("Input options"));
+        parser.add_template(Template::new().matches("-v"));
+        parser.add_template(Template::new().matches("--quiet").group("Output options"));
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        let position = |s: &str| help.find(s).unwrap();
+        assert!(position("Arguments:") < position("    -v "));
+        assert!(position("    -v ") < position("    -h, --help"));
+        assert!(position("    -h, --help") < position("Output options:"));
+        assert!(position("Output options:") < position("    --out "));
+        assert!(position("    --quiet ") < position("Input options:"));
+        assert!(position("Input options:") < position("    --in "));
+    }
+
+    #[test]
+    fn hidden() {
+        let mut parser = Parser::new().exit_on_help(false);
+        parser.add("--visible", 0, "A visible flag.");
+        let debug = parser.add_template(
+            Template::new()
+                .matches("--debug")
+                .with_help("Debug stuff.")
+                .hidden(true),
+        );
+        parser
+            .add_subcommand(debug, "dump", 0, "Dump the state.")
+            .unwrap();
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        assert!(help.contains("--visible"));
+        assert!(!help.contains("--debug"));
+        assert!(!help.contains("dump"));
+        assert!(!parser.generate_manpage().contains("debug"));
+
+        let result = parser.parse(Some(vec!["--debug", "dump"])).unwrap();
+        assert!(result.has("--debug"));
+        assert!(result.has_with_context(debug, "dump"));
+    }
+
+    #[test]
+    fn help_width() {
+        let mut parser = Parser::new().exit_on_help(false).help_width(50);
+        parser.add(
+            "--long",
+            0,
+            "This help string is deliberately long, so that it has to be wrapped.",
+        );
+
+        let help = parser.parse(Some(vec!["-h"])).unwrap_err().to_string();
+        let lines: Vec<&str> = help
+            .lines()
+            .skip_while(|l| !l.starts_with("    --long"))
+            .take(2)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "    --long     This help string is deliberately",
+                "               long, so that it has to be wrapped.",
+            ]
+        );
+        assert!(help.lines().all(|l| l.len() <= 50));
+    }
+
+    #[test]
+    fn print_help() {
+        let parser = Parser::new().set_help("Custom help.");
+        let mut output = Vec::new();
+
+        parser.print_help(&mut output).unwrap();
+        assert_eq!(output, b"Custom help.\n");
+    }
+
+    #[test]
+    fn help_string() {
+        let mut parser = Parser::new().with_program_name("myprog");
+        parser.add("--say", 1, "Say something.");
+
+        assert!(parser.help_string().starts_with("myprog"));
+        assert!(parser.help_string().contains("Say something."));
+        assert_eq!(parser.set_help("Custom.").help_string(), "Custom.");
+    }
+
+    #[test]
+    fn dispatch() {
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut parser = Parser::new();
+        let log = fired.clone();
+        let remote = parser.add_template(
+            Template::new()
+                .matches("remote")
+                .on_parse(move |_| log.lock().unwrap().push("remote".to_string())),
+        );
+        let log = fired.clone();
+        parser
+            .add_subcommand_template(
+                remote,
+                Template::new().matches("add").on_parse(move |args| {
+                    log.lock().unwrap().push(format!("add {}", args.join(" ")))
+                }),
+            )
+            .unwrap();
+
+        parser
+            .dispatch(Some(vec!["remote", "add", "origin", "url"]))
+            .unwrap();
+        assert_eq!(*fired.lock().unwrap(), vec!["add origin url"]);
+
+        parser.dispatch(Some(vec!["remote"])).unwrap();
+        assert_eq!(*fired.lock().unwrap(), vec!["add origin url", "remote"]);
+    }
+
+    #[test]
+    fn greedy() {
+        let mut parser = Parser::new();
+        let files = parser.add_template(
+            Template::new()
+                .matches("--files")
+                .variadic(true)
+                .greedy(true),
+        );
+        parser
+            .add_subcommand(files, "sub", 0, "A subcommand.")
+            .unwrap();
+        parser.add("-v", 0, "Verbose.");
+
+        let result = parser
+            .parse(Some(vec![
+                "--files", "a", "-b", "sub", "-v", "--files", "c", "--", "d",
+            ]))
+            .unwrap();
+        assert_eq!(result.get_with_id(files).unwrap().values(), &vec!["c"]);
+        assert_eq!(result.count_with_id(files), 2);
+        assert!(result.has("-v"));
+
+        let result = parser
+            .parse(Some(vec!["--files", "a", "-b", "sub", "-v"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(files).unwrap().values(),
+            &vec!["a", "-b", "sub"]
+        );
+        assert_eq!(result.trailing(), &Vec::<String>::new());
+    }
+
+    #[test]
+    fn error_positions() {
+        let mut parser = Parser::new().strict(true);
+        parser.add("--say", 1, "Say something.");
+        parser.add("-a", 0, "A flag.");
+        parser.add("-b", 0, "A flag.");
+
+        assert_eq!(
+            parser
+                .parse(Some(vec!["--say", "hi", "--say"]))
+                .unwrap_err(),
+            HpError::NumberOfValues("--say".into(), 0, 1, 2)
+        );
+        assert_eq!(
+            parser.parse(Some(vec!["-ab", "-c"])).unwrap_err(),
+            HpError::UnknownArgument("-c".into(), Some("-a".into()), 1)
+        );
+        assert_eq!(
+            parser.parse(Some(vec!["-a", "--say", "-b"])).unwrap_err(),
+            HpError::ValueIsFlag("--say".into(), "-b".into(), 1)
+        );
+    }
+
+    #[test]
+    fn key_value() {
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("-D")
+                .matches("--define")
+                .key_value(true),
+        );
+
+        let result = parser
+            .parse(Some(vec![
+                "-D",
+                "name=hp",
+                "--define=version=1.0",
+                "-D",
+                "name=x",
+            ]))
+            .unwrap();
+        let map = result.get_map("--define");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["name"], "x");
+        assert_eq!(map["version"], "1.0");
+        assert!(result.get_map("--unknown").is_empty());
+        assert!(parser.help_string().contains("-D | --define [KEY=VALUE]"));
+
+        assert_eq!(
+            parser.parse(Some(vec!["-D", "name"])).unwrap_err(),
+            HpError::MalformedKeyValue("name".into())
+        );
+    }
+
+    #[test]
+    fn parse_from() {
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say something.");
+
+        let args = String::from("--say hello extra");
+        let result = parser
+            .parse_from(args.split_whitespace().map(String::from))
+            .unwrap();
+        assert_eq!(result.get_with_id(say).unwrap().values(), &vec!["hello"]);
+        assert_eq!(result.positionals(), &vec!["extra"]);
+    }
+
+    #[test]
+    fn supplied_values() {
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--color")
+                .number_of_values(1)
+                .optional_values(true),
+        );
+        parser.add_template(
+            Template::new()
+                .matches("--level")
+                .number_of_values(1)
+                .optional_values(true)
+                .default_value("1"),
+        );
+
+        let result = parser.parse(Some(vec!["--color", "--level"])).unwrap();
+        let color = result.get("--color").unwrap();
+        assert!(color.is_empty() && !color.was_supplied_value());
+        let level = result.get("--level").unwrap();
+        assert!(!level.is_empty() && !level.was_supplied_value());
+
+        let result = parser.parse(Some(vec!["--level", "2"])).unwrap();
+        assert!(result.get("--level").unwrap().was_supplied_value());
+        assert!(!result.has("--color"));
+    }
+
+    #[test]
+    fn value_is_flag() {
+        let mut parser = Parser::new();
+        parser.add("--say", 1, "Say something.");
+        parser.add("--verbose", 0, "Print more information.");
+
+        assert_eq!(
+            parser.parse(Some(vec!["--say", "--verbose"])).unwrap_err(),
+            HpError::ValueIsFlag("--say".into(), "--verbose".into(), 0)
+        );
+        assert_eq!(
+            parser.parse(Some(vec!["--say", "--"])).unwrap_err(),
+            HpError::NumberOfValues("--say".into(), 0, 1, 0)
+        );
+    }
+
+    #[test]
+    fn template_equality() {
+        let plain = Template::new().matches("--say").number_of_values(1);
+        let with_action = plain.clone().on_parse(|_| ());
+
+        assert_eq!(plain, with_action);
+        assert_ne!(plain, plain.clone().required(true));
+        assert!(format!("{with_action:?}").contains("action: Some(<closure>)"));
+        assert!(format!("{:?}", Parser::new()).starts_with("Parser {"));
+    }
+
+    #[test]
+    fn at_least() {
+        let mut parser = Parser::new().exit_on_help(false);
+        let add = parser.add_template(Template::new().matches("add").at_least(2));
+        parser.add("-v", 0, "Verbose.");
+
+        let result = parser
+            .parse(Some(vec!["add", "1", "2", "3", "-v"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(add).unwrap().values(),
+            &vec!["1", "2", "3"]
+        );
+        assert_eq!(
+            parser.parse(Some(vec!["add", "1"])).unwrap_err(),
+            HpError::NumberOfValues("add".into(), 1, 2, 0)
+        );
+        assert!(parser.help_string().contains("add [2 or more value/s]"));
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut parser = Parser::new();
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        parser.add_template(
+            Template::new()
+                .matches("-v")
+                .on_parse(move |_| *counter.lock().unwrap() += 1),
+        );
+        assert_send_sync(&parser);
+
+        std::thread::spawn(move || parser.parse(Some(vec!["-v", "-v"])).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn parse_str() {
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say something.");
+        parser.add("--verbose", 0, "Print more information.");
+
+        let result = parser
+            .parse_str(r#"--say "hello world" --verbose"#)
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(say).unwrap().values(),
+            &vec!["hello world"]
+        );
+        assert!(result.has("--verbose"));
+        assert!(parser.parse_str("--say \"hello").is_err());
+    }
+
+    #[test]
+    fn help_footer() {
+        let mut parser = Parser::new()
+            .with_program_name("myprog")
+            .with_footer("Report bugs to the issue tracker.");
+        parser.add("--say", 1, "Say something.");
+        parser.add_example("myprog --say hi", "Print 'hi'.");
+
+        let help = parser.help_string();
+        assert!(help.ends_with(
+            "Examples:\n    $ myprog --say hi\n        Print 'hi'.\nReport bugs to the issue tracker."
+        ));
+    }
+
+    #[test]
+    fn no_usage() {
+        let mut parser = Parser::new().with_program_name("myprog");
+        parser.add("--say", 1, "Say something.");
+        assert!(parser.help_string().contains("Usage:"));
+
+        let parser = parser.with_usage("myprog [options]").no_usage();
+        let help = parser.help_string();
+        assert!(!help.contains("Usage:") && !help.contains("[options]"));
+        assert!(help.contains("--say"));
+    }
+
+    #[test]
+    fn usage_placeholders() {
+        let mut parser = Parser::new()
+            .with_usage("{prog} {--output} {-x} {unknown} {prog")
+            .with_program_name("myprog");
+        parser.add_template(
+            Template::new()
+                .matches("--output")
+                .number_of_values(1)
+                .value_name("FILE"),
+        );
+        parser.add("-x", 0, "A flag.");
+
+        assert!(parser
+            .help_string()
+            .contains("Usage:\n    myprog --output <FILE> -x {unknown} {prog\n"));
+    }
+
+    #[test]
+    fn stats() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database commands.");
+        parser
+            .add_subcommand(db, "--url", 1, "Database URL.")
+            .unwrap();
+        parser.add("-v", 0, "Verbose.");
+
+        let result = parser
+            .parse(Some(vec![
+                "db",
+                "--url",
+                "localhost",
+                "extra",
+                "-v",
+                "--",
+                "x",
+            ]))
+            .unwrap();
+        assert_eq!(
+            result.stats(),
+            ParseStats {
+                tokens_consumed: 4,
+                matched_count: 3,
+                max_context_depth: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn tree() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database commands.");
+        let url = parser
+            .add_subcommand(db, "--url", 1, "Database URL.")
+            .unwrap();
+        parser.add_subcommand(url, "--tls", 0, "Use TLS.").unwrap();
+        parser.add_subcommand(db, "--user", 1, "User.").unwrap();
+        parser.add("-v", 0, "Verbose.");
+
+        let result = parser
+            .parse(Some(vec!["-v", "db", "--url", "localhost", "--tls"]))
+            .unwrap();
+        let tree = result.tree();
+        assert!(tree.argument().is_none());
+        let names: Vec<&str> = tree.children().iter().map(|n| n.name()).collect();
+        assert_eq!(names, vec!["db", "-v"]);
+
+        let db = &tree.children()[0];
+        assert_eq!(db.children().len(), 1);
+        let url = &db.children()[0];
+        assert_eq!(
+            (url.name(), url.values()),
+            ("--url", &["localhost".into()][..])
+        );
+        assert_eq!(url.children()[0].name(), "--tls");
+        assert!(url.children()[0].children().is_empty());
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["", "two words"] {
+            let mut parser = Parser::new();
+            parser.add_template(Template::new().matches("--ok").matches(name));
+            assert_eq!(
+                parser.validate(),
+                Err(HpError::InvalidMatchName(name.into()))
+            );
+        }
+    }
+
+    #[test]
+    fn hash_in_names() {
+        let mut parser = Parser::new();
+        let tag = parser.add("#tag", 1, "Add a tag.");
+        let sub = parser.add("0", 0, "A command named like a context.");
+        parser
+            .add_subcommand(sub, "#tag", 0, "A subcommand.")
+            .unwrap();
+
+        let result = parser.parse(Some(vec!["#tag", "x"])).unwrap();
+        assert_eq!(result.get("#tag").unwrap().values(), &vec!["x"]);
+        assert_eq!(result.get_with_id(tag).unwrap().values(), &vec!["x"]);
+        assert!(!result.has_with_context(sub, "#tag"));
+
+        let result = parser.parse(Some(vec!["0", "#tag"])).unwrap();
+        assert!(result.has_with_context(sub, "#tag"));
+        assert!(!result.has("#tag"));
+    }
+
+    #[test]
+    fn normalize_dashes() {
+        let mut parser = Parser::new().normalize_dashes(true);
+        parser.add("--verbose", 0, "Print more information.");
+        parser.add("say", 1, "Say something.");
+
+        let result = parser.parse(Some(vec!["verbose", "--say", "hi"])).unwrap();
+        assert!(result.has("verbose") && result.has("--verbose") && result.has("-verbose"));
+        assert_eq!(result.get("say").unwrap().values(), &vec!["hi"]);
+
+        let mut plain = Parser::new();
+        plain.add("--verbose", 0, "Print more information.");
+        let result = plain.parse(Some(vec!["verbose"])).unwrap();
+        assert!(!result.has("verbose") && !result.has("--verbose"));
+
+        let mut late = Parser::new();
+        late.add("--verbose", 0, "Print more information.");
+        late.add("--v", 0, "A flag.");
+        let value = late.add("v", 1, "A value.");
+        let late = late.normalize_dashes(true);
+        assert_eq!(late.validate(), Err(HpError::DuplicateMatch("v".into())));
+        assert_eq!(late.template_id_of("-v"), Some(value));
+        assert!(late.template_id_of("verbose").is_some());
+
+        let restored = late.normalize_dashes(false);
+        assert!(restored.template_id_of("--verbose").is_some());
+        assert!(restored.template_id_of("verbose").is_none());
+    }
+
+    #[test]
+    fn parser_macro() {
+        let parser = parser! {
+            name: "myprog",
+            args: {
+                "--say"(1) => "Say something.",
+                "-c"(0) => "Count something." {
+                    "--add"(2) => "Add two numbers." {
+                        "--twice"(0) => "Add twice."
+                    },
+                    "--sub"(2) => "Subtract two numbers.",
+                },
+                "-v"(0) => "Verbose."
+            }
+        };
+
+        let result = parser
+            .parse(Some(vec!["-c", "--add", "1", "2", "--twice"]))
+            .unwrap();
+        let c = result.get("-c").unwrap().id();
+        let add = result.get_with_context(c, "--add").unwrap();
+        assert_eq!(add.values(), &vec!["1", "2"]);
+        assert!(result.has_with_context(add.id(), "--twice"));
+        assert!(parser.help_string().starts_with("myprog"));
+        assert!(parser
+            .parse(Some(vec!["-c", "--sub", "1", "2", "-v"]))
+            .is_ok());
+        assert_eq!(parser.templates().len(), 6);
+    }
+
+    #[test]
+    fn rest() {
+        let mut parser = Parser::new();
+        let exec = parser.add_template(Template::new().matches("--exec").rest(true));
+        parser.add("-v", 0, "Verbose.");
+
+        let result = parser
+            .parse(Some(vec!["-v", "--exec", "ls", "-v", "--", "x"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(exec).unwrap().values(),
+            &vec!["ls", "-v", "--", "x"]
+        );
+        assert_eq!(result.count("-v"), 1);
+        assert!(parser.parse(Some(vec!["--exec"])).is_ok());
+
+        parser.add_template(Template::new().matches("--also").rest(true));
+        assert_eq!(
+            parser.validate(),
+            Err(HpError::MultipleRest(vec![
+                "--exec".into(),
+                "--also".into()
+            ]))
+        );
+    }
+
+    #[test]
+    fn deprecated() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let w = warnings.clone();
+        let mut parser = Parser::new().on_deprecation(move |name, replacement| {
+            w.lock().unwrap().push(format!("{name} -> {replacement}"));
+        });
+        parser.add_template(
+            Template::new()
+                .matches("--old-flag")
+                .matches("-o")
+                .number_of_values(1)
+                .deprecated("--new-flag"),
+        );
+        parser.add("--new-flag", 1, "The new flag.");
+
+        let result = parser
+            .parse(Some(vec!["-o", "x", "--new-flag", "y"]))
+            .unwrap();
+        assert_eq!(result.get("-o").unwrap().values(), &vec!["x"]);
+        assert_eq!(*warnings.lock().unwrap(), vec!["-o -> --new-flag"]);
+    }
+
+    #[test]
+    fn dash_values() {
+        let mut parser = Parser::new();
+        parser.add("-v", 0, "Verbose.");
+        parser.add("--message", 1, "Message.");
+        parser.add_template(
+            Template::new()
+                .matches("--raw")
+                .number_of_values(1)
+                .accept_dash_values(true),
+        );
+
+        let result = parser.parse(Some(vec!["--message=-v"])).unwrap();
+        assert_eq!(result.get("--message").unwrap().values(), &vec!["-v"]);
+        assert_eq!(
+            parser.parse(Some(vec!["--message", "-v"])).unwrap_err(),
+            HpError::ValueIsFlag("--message".into(), "-v".into(), 0)
+        );
+
+        let result = parser.parse(Some(vec!["--raw", "-v", "-v"])).unwrap();
+        assert_eq!(result.get("--raw").unwrap().values(), &vec!["-v"]);
+        assert_eq!(result.count("-v"), 1);
+    }
+
+    #[test]
+    fn value_names() {
+        let output = Template::new()
+            .matches("--output")
+            .number_of_values(1)
+            .value_name("FILE");
+        assert_eq!(output.values_label().unwrap(), "<FILE>");
+        let copy = Template::new()
+            .matches("--copy")
+            .number_of_values(3)
+            .value_names(vec!["SRC".into(), "DEST".into()]);
+        assert_eq!(copy.values_label().unwrap(), "<SRC> <DEST> <DEST>");
+        let files = Template::new()
+            .matches("--files")
+            .variadic(true)
+            .optional_values(true)
+            .value_name("FILE");
+        assert_eq!(files.values_label().unwrap(), "[<FILE>...]");
+
+        let mut parser = Parser::new();
+        parser.add_template(output.with_help("Write to a file."));
+        assert!(parser.help_string().contains("--output <FILE>"));
+    }
+
+    #[test]
+    fn negatable() {
+        let mut parser = Parser::new().strict(true);
+        parser.add_template(Template::new().matches("--color").negatable(true));
+        parser.add("--verbose", 0, "Verbose.");
+
+        assert_eq!(parser.parse(Some(vec![])).unwrap().flag("--color"), None);
+        let result = parser.parse(Some(vec!["--color"])).unwrap();
+        assert_eq!(result.flag("--color"), Some(true));
+        let result = parser.parse(Some(vec!["--color", "--no-color"])).unwrap();
+        assert_eq!(result.flag("--color"), Some(false));
+        assert!(parser.parse(Some(vec!["--no-verbose"])).is_err());
+        assert!(parser.help_string().contains("--color | --no-color"));
 
-        let longest_value_len = self
-            .stored
-            .values()
-            .into_iter()
-            .map(|t| {
-                let mut temp = t.matches.join(" | ");
-                if t.num_values > 0 {
-                    let optional = match t.optional_vals {
-                        true => " optional ",
-                        false => " ",
-                    };
-                    write!(temp, " [{}{optional}value/s]", t.num_values).unwrap();
-                }
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let log = fired.clone();
+        let mut parser = Parser::new();
+        parser.add_template(
+            Template::new()
+                .matches("--color")
+                .negatable(true)
+                .on_parse_ctx(move |name, _, _| log.lock().unwrap().push(name.to_string())),
+        );
+        let result = parser
+            .parse(Some(vec!["--color", "--no-color", "--no-color"]))
+            .unwrap();
+        assert_eq!(*fired.lock().unwrap(), vec!["--color"]);
+        assert!(result.has("--color"));
+        assert_eq!(result.count("--color"), 1);
+        assert_eq!(result.flag("--color"), Some(false));
 
-                temp.len()
-            })
-            .max();
+        let result = parser.parse(Some(vec!["--no-color"])).unwrap();
+        assert!(result.has("--color") && result.count("--color") == 0);
+        assert_eq!(fired.lock().unwrap().len(), 1);
+    }
 
-        if !self.program_name.is_empty() {
-            write!(result_string, "{}", self.program_name).unwrap_or(());
-        }
-        if !self.description.is_empty() {
-            writeln!(result_string, ": {}", self.description).unwrap_or(());
-        }
-        if !self.author.is_empty() {
-            writeln!(result_string, "Author: {}", self.author).unwrap_or(());
-        }
-        if !self.usage.is_empty() {
-            writeln!(result_string, "Usage:\n    {}", self.usage).unwrap_or(());
-        } else {
-            writeln!(
-                result_string,
-                "Usage:\n    $ {} -[-command] [value/s...]",
-                self.program_name
-            )
-            .unwrap_or(());
-        }
+    #[test]
+    fn matched_alias() {
+        let mut parser = Parser::new();
+        let expand = parser.add_template(Template::new().matches("-x").matches("--expand"));
 
-        let longest_value_len = match longest_value_len {
-            Some(l) => l + 4,
-            None => 4,
-        };
-        let mut max_level = 0;
+        let result = parser.parse(Some(vec!["--expand", "-x"])).unwrap();
+        assert_eq!(result.get("--expand").unwrap().matched(), "--expand");
+        assert_eq!(result.get_with_id(expand).unwrap().matched(), "-x");
+        let result = parser.parse(Some(vec!["-x"])).unwrap();
+        assert_eq!(result.get("--expand").unwrap().matched(), "-x");
+    }
 
-        writeln!(result_string, "Arguments:").unwrap_or(());
+    #[test]
+    fn ancestor_contexts() {
+        let mut parser = Parser::new();
+        let db = parser.add("db", 0, "Database.");
+        let migrate = parser.add_subcommand(db, "migrate", 0, "Migrate.").unwrap();
+        let up = parser.add_subcommand(migrate, "up", 0, "Up.").unwrap();
+        let down = parser.add_subcommand(migrate, "down", 0, "Down.").unwrap();
+        parser.add_subcommand(up, "--dry", 0, "Dry run.").unwrap();
+        let seed = parser.add_subcommand(db, "seed", 0, "Seed.").unwrap();
+        parser.add("--verbose", 0, "Verbose.");
 
-        let mut template_vec: Vec<(&Template, usize)> = Vec::new();
-        for name in self.order.iter() {
-            let each = self
-                .stored
-                .values()
-                .find(|temp| temp.matches.contains(name))
-                .unwrap();
-            if !template_vec
-                .iter()
-                .any(|(template, _)| template.id == each.id)
-            {
-                if let Some(sub_arg_of) = each.subargument_of {
-                    if let Some((index, (_, level))) = template_vec
-                        .iter()
-                        .enumerate()
-                        .find(|(_, (t, _))| t.id == sub_arg_of)
-                    {
-                        if level + 1 > max_level {
-                            max_level = level + 1;
-                        }
-                        template_vec.insert(index + 1, (each, level + 1));
-                    }
-                } else {
-                    template_vec.push((each, 0))
-                }
-            }
-        }
+        let result = parser
+            .parse(Some(vec![
+                "db",
+                "migrate",
+                "up",
+                "--dry",
+                "down",
+                "seed",
+                "--verbose",
+            ]))
+            .unwrap();
+        assert!(result.has_with_context(up, "--dry"));
+        assert!(result.has_with_context(migrate, "down"));
+        assert!(result.has_with_context(db, "seed"));
+        assert!(result.has("--verbose"));
+        assert_eq!(result.get_with_id(down).unwrap().matched(), "down");
+        assert!(result.has_with_id(seed));
 
-        for (template, level) in template_vec.iter() {
-            let mut lvl = String::new();
-            (0..(level * 4)).for_each(|_| lvl.push(' '));
+        assert!(parser.parse(Some(vec!["db", "seed", "up"])).is_err());
+    }
 
-            let mut matches = template.matches.join(" | ");
-            if template.num_values > 0 {
-                let optional = match template.optional_vals {
-                    true => " optional ",
-                    false => " ",
-                };
-                write!(matches, " [{}{optional}value/s]", template.num_values).unwrap();
-            }
+    #[test]
+    fn merge() {
+        let mut logging = Parser::new();
+        let verbose = logging.add("--verbose", 0, "Verbose.");
+        let level = logging.add("--level", 1, "Level.");
+        logging
+            .add_subcommand(level, "--color", 0, "Color.")
+            .unwrap();
 
-            while matches.len() != longest_value_len + (max_level * 4) - lvl.len() {
-                matches.push(' ');
-            }
+        let mut parser = Parser::new();
+        let say = parser.add("--say", 1, "Say.");
+        let ids = parser.merge(logging);
+        assert_ne!(ids[&verbose], say);
 
-            writeln!(result_string, "    {lvl}{matches} {}", template.help).unwrap_or(());
-        }
+        let result = parser
+            .parse(Some(vec![
+                "--say",
+                "x",
+                "--level",
+                "1",
+                "--color",
+                "--verbose",
+            ]))
+            .unwrap();
+        assert!(result.has_with_id(ids[&verbose]));
+        assert!(result.has_with_context(ids[&level], "--color"));
 
-        let mut help = String::from("-h, --help");
-        while help.len() != longest_value_len + max_level * 4 {
-            help.push(' ');
-        }
+        let mut other = Parser::new();
+        other.add("--say", 0, "Say again.");
+        parser.merge(other);
+        assert_eq!(
+            parser.validate(),
+            Err(HpError::DuplicateMatch("--say".into()))
+        );
+    }
 
-        write!(result_string, "    {help} Print this help message!").unwrap_or(());
+    #[test]
+    fn require() {
+        let mut parser = Parser::new();
+        parser.add("--input", 1, "Input.");
+        parser.add("--output", 1, "Output.");
 
-        result_string
+        let result = parser.parse(Some(vec!["--input", "a"])).unwrap();
+        assert_eq!(result.require_value("--input", 0), Ok("a"));
+        assert_eq!(
+            result.require_value("--input", 1),
+            Err(HpError::IndexOutOfRange(1, 1))
+        );
+        assert_eq!(
+            result.require("--output").unwrap_err(),
+            HpError::MissingRequired(vec!["--output".into()])
+        );
     }
 
-    fn help_and_exit(&self) {
-        if let Some(help) = &self.help {
-            println!("{help}");
-        } else {
-            let help_string = self.create_help();
+    #[test]
+    fn sort_help() {
+        let build = |mode| {
+            let mut parser = Parser::new().sort_help(mode).with_usage("x");
+            parser.add_template(Template::new().matches("--zeta").group("Output"));
+            parser.add("--beta", 0, "Beta.");
+            parser.add_template(Template::new().matches("--alpha").group("Input"));
+            let c = parser.add("-c", 0, "C.");
+            parser.add_subcommand(c, "--sub-b", 0, "B.").unwrap();
+            parser.add_subcommand(c, "--sub-a", 0, "A.").unwrap();
+            parser.help_string()
+        };
+        let position = |help: &str, name: &str| help.find(name).unwrap();
 
-            println!("{help_string}");
+        for mode in [
+            SortMode::Insertion,
+            SortMode::Alphabetical,
+            SortMode::Grouped,
+        ] {
+            assert_eq!(build(mode), build(mode));
         }
 
-        if self.exit_on_help {
-            exit(0);
-        }
-    }
+        let help = build(SortMode::Insertion);
+        assert!(position(&help, "--beta") < position(&help, "-c"));
+        assert!(position(&help, "--sub-b") < position(&help, "--sub-a"));
+        assert!(position(&help, "Output:") < position(&help, "Input:"));
 
-    /// Parse the command line arguments, or a list of strings, if provided, and return a
-    /// `ParsedArguments` structure.
-    pub fn parse(&mut self, from: Option<Vec<&str>>) -> Result<ParsedArguments, HpError> {
-        let args: Vec<String>;
-        if let Some(from_vec) = from {
-            args = from_vec.iter().map(|each| each.to_string()).collect();
-        } else {
-            args = env::args().collect();
-        }
+        let help = build(SortMode::Alphabetical);
+        assert!(position(&help, "--sub-a") < position(&help, "--sub-b"));
+        assert!(position(&help, "--beta") < position(&help, "-c"));
+        assert!(position(&help, "Input:") < position(&help, "Output:"));
 
-        let mut hm = HashMap::new();
-        let mut idhm = HashMap::new();
+        let help = build(SortMode::Grouped);
+        assert!(position(&help, "--sub-b") < position(&help, "--sub-a"));
+        assert!(position(&help, "Input:") < position(&help, "Output:"));
+    }
 
-        let mut context = 0;
+    #[test]
+    fn config_file() {
+        let path = env::temp_dir().join("hp_test_config.conf");
+        std::fs::write(
+            &path,
+            "# defaults\ncolor = never\nsay = from file\nquiet = false\n",
+        )
+        .unwrap();
 
-        for (index, arg) in args.iter().enumerate() {
-            if arg == "--help" || arg == "-h" {
-                self.help_and_exit()
-            }
-            let query = format!("{context}#{arg}");
-            let query2 = format!("0#{arg}");
-
-            if self.stored.get(&query).is_some() {
-                if let Some(template) = self.stored.get(&query) {
-                    context = template.id;
-                    let mut i = index;
-                    let mut count = 0;
-                    let mut values: Vec<String> = Vec::new();
-
-                    while i < index + template.num_values {
-                        i += 1;
-                        if i == args.len() {
-                            break;
-                        }
-                        let value = &args[i];
-
-                        let q1 = format!("{context}#{value}");
-                        let q2 = format!("0#{value}");
-
-                        if self.stored.get(&q1).is_some() || self.stored.get(&q2).is_some() {
-                            break;
-                        } else {
-                            values.push(value.to_string());
-                            count += 1;
-                        }
-                    }
+        let mut parser = Parser::new().with_config_file(&path);
+        let color = parser.add("--color", 1, "Colors.");
+        let say = parser.add("--say", 1, "Say.");
+        let quiet = parser.add("--quiet", 0, "Quiet.");
 
-                    if !template.optional_vals && count < template.num_values {
-                        return Err(HpError::NumberOfValues(
-                            arg.into(),
-                            count,
-                            template.num_values,
-                        ));
-                    }
+        let result = parser.parse(Some(vec!["--say", "hi"])).unwrap();
+        assert_eq!(result.get_with_id(color).unwrap().values(), &vec!["never"]);
+        assert_eq!(result.get_with_id(say).unwrap().values(), &vec!["hi"]);
+        assert!(!result.has_with_id(quiet));
 
-                    if let Some(action) = &template.action {
-                        action.borrow_mut()(values.clone());
-                    }
+        std::fs::write(&path, "color never").unwrap();
+        assert!(matches!(
+            parser.parse(Some(vec![])),
+            Err(HpError::ConfigParseError(1, _))
+        ));
+        let parser = Parser::new().with_config_file("/this/file/does/not/exist");
+        assert!(parser.parse(Some(vec![])).is_ok());
+    }
 
-                    let pa = ParsedArgument::new(template.id, values);
-                    hm.insert(query, pa.clone());
-                    idhm.insert(template.id, pa);
-                }
-            } else if let Some(template) = self.stored.get(&query2) {
-                context = template.id;
-                let mut i = index;
-                let mut count = 0;
-                let mut values: Vec<String> = Vec::new();
-
-                while i < index + template.num_values {
-                    i += 1;
-                    if i == args.len() {
-                        break;
-                    }
-                    let value = &args[i];
+    #[test]
+    fn prompt() {
+        let mut parser = Parser::new().allow_prompts(true);
+        parser.add_template(
+            Template::new()
+                .matches("--name")
+                .number_of_values(1)
+                .required(true)
+                .prompt("Your name: "),
+        );
 
-                    let q1 = format!("{context}#{value}");
-                    let q2 = format!("0#{value}");
+        let result = parser.parse(Some(vec!["--name", "me"])).unwrap();
+        assert_eq!(result.get("--name").unwrap().values(), &vec!["me"]);
+        if !io::IsTerminal::is_terminal(&io::stdin()) {
+            assert_eq!(
+                parser.parse(Some(vec![])).unwrap_err(),
+                HpError::MissingRequired(vec!["--name".into()])
+            );
+        }
+    }
 
-                    if self.stored.get(&q1).is_some() || self.stored.get(&q2).is_some() {
-                        break;
-                    } else {
-                        values.push(value.to_string());
-                        count += 1;
-                    }
-                }
+    #[test]
+    fn fold() {
+        let mut parser = Parser::new();
+        let sum = parser.add_template(
+            Template::new()
+                .matches("--add")
+                .number_of_values(1)
+                .fold(0, |sum, values| sum + values[0].parse::<i32>().unwrap_or(0)),
+        );
+        let words = parser.add_template(Template::new().matches("-w").variadic(true).fold(
+            Vec::new(),
+            |mut words: Vec<String>, values| {
+                words.extend(values);
+                words
+            },
+        ));
 
-                if !template.optional_vals && count < template.num_values {
-                    return Err(HpError::NumberOfValues(
-                        arg.into(),
-                        count,
-                        template.num_values,
-                    ));
-                }
+        let result = parser
+            .parse(Some(vec![
+                "--add", "1", "-w", "a", "b", "--add", "2", "-w", "c",
+            ]))
+            .unwrap();
+        assert_eq!(result.folded::<i32>(sum), Some(3));
+        assert_eq!(
+            result.folded::<Vec<String>>(words).unwrap(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(result.folded::<String>(sum), None);
+        assert_eq!(parser.parse(Some(vec![])).unwrap().folded::<i32>(sum), None);
+    }
 
-                if let Some(action) = &template.action {
-                    action.borrow_mut()(values.clone());
-                }
+    #[test]
+    fn all_aliases() {
+        let mut parser = Parser::new();
+        let expand = parser.add_template(Template::new().matches("-x").matches("--expand"));
+        parser.add_subcommand(expand, "-s", 0, "String.").unwrap();
+        parser
+            .add_subcommand_template(expand, Template::new().matches("-i").matches("--int"))
+            .unwrap();
 
-                let pa = ParsedArgument::new(template.id, values);
-                hm.insert(query2, pa.clone());
-                idhm.insert(template.id, pa);
-            } else if let Some(template) = self.stored.values().find(|t| t.matches.contains(arg)) {
-                if let Some(parent) = template.subargument_of {
-                    let parent = self.stored.values().find(|t| t.id == parent).unwrap();
-                    let parent_match = &parent.matches[0];
-                    return Err(HpError::OutOfContext(
-                        arg.to_string(),
-                        parent_match.to_string(),
-                    ));
-                }
-            }
-        }
+        let result = parser.parse(Some(vec!["-x", "--int"])).unwrap();
+        assert!(result.has("-x") && result.has("--expand"));
+        assert!(result.has_with_context(expand, "-i"));
+        assert!(result.has_path(&["--expand", "--int"]));
+        assert!(!result.has_with_context(expand, "-s"));
 
-        Ok(ParsedArguments { hm, ids: idhm })
+        let result = parser.parse(Some(vec!["-x"])).unwrap();
+        assert_eq!(result.len(), 1);
+        let names: Vec<&str> = result.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["-x"]);
+        assert_eq!(result.to_json(), r#"{"-x":[]}"#);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
     #[test]
-    fn help() {
-        let mut parser = Parser::new()
-            .with_usage("")
-            .with_author("me")
-            .with_description("Example program")
-            .exit_on_help(false);
+    fn explain() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let log = lines.clone();
+        let mut parser =
+            Parser::new().on_explain(move |line| log.lock().unwrap().push(line.to_string()));
+        let c = parser.add("-c", 0, "Count.");
+        parser.add_subcommand(c, "--add", 2, "Add.").unwrap();
+        parser.add("-a", 0, "A.");
+        parser.add("-b", 0, "B.");
 
-        parser.add("--say", 0, "Repeat something");
-        let expand = parser.add_template(
-            Template::new()
-                .matches("-x")
-                .matches("--expand")
-                .optional_values(false)
-                .number_of_values(0)
-                .with_help("Expand something."),
+        let result = parser
+            .parse(Some(vec!["-c", "--add", "1", "2", "pos", "-ab", "--", "x"]))
+            .unwrap();
+        assert!(result.has_path(&["-c", "--add"]));
+        assert_eq!(result.positionals(), &vec!["pos"]);
+        assert_eq!(result.count("-a"), 1);
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "'-c' matches the key (0, '-c') in the current context 0",
+                "'-c' takes 0 value/s, the values end because the maximum is 0",
+                "'--add' matches the key (1, '--add') in the current context 1",
+                "'--add' takes 2 value/s, the values end because the maximum is 2",
+                "'pos' is not an argument in context 3, it's a positional argument",
+                "'-ab' is split into '-a', '-b'",
+                "'-a' matches the key (0, '-a') at the top level",
+                "'-a' takes 0 value/s, the values end because the maximum is 0",
+                "'-b' matches the key (0, '-b') at the top level",
+                "'-b' takes 0 value/s, the values end because the maximum is 0",
+                "'--' ends the arguments, 1 trailing argument/s",
+            ]
+        );
+
+        lines.lock().unwrap().clear();
+        parser.add_template(Template::new().matches("--list").variadic(true));
+        parser.parse(Some(vec!["--list", "x", "-b"])).unwrap();
+        assert_eq!(
+            lines.lock().unwrap()[1],
+            "'--list' takes 1 value/s, the values end because '-b' is an argument"
         );
+    }
 
-        let sub_sub = parser.add_subcommand_template(
-            expand,
+    #[test]
+    fn stop_at() {
+        let mut parser = Parser::new();
+        let repeat = parser.add_template(
             Template::new()
-                .matches("--string")
-                .number_of_values(0)
-                .with_help("Expands a string"),
+                .matches("repeat")
+                .variadic(true)
+                .greedy(true)
+                .stop_at(vec!["then".into(), "else".into()]),
         );
 
-        let sub_sub_sub = parser.add_subcommand(sub_sub, "--super-test", 0, "Amazing super test.");
+        let result = parser
+            .parse(Some(vec!["repeat", "a", "-b", "then", "c"]))
+            .unwrap();
+        assert_eq!(
+            result.get_with_id(repeat).unwrap().values(),
+            &vec!["a", "-b"]
+        );
+        assert_eq!(result.positionals(), &vec!["then", "c"]);
+        assert_eq!(
+            parser.parse(Some(vec!["repeat", "then"])).unwrap_err(),
+            HpError::NumberOfValues("repeat".into(), 0, 1, 0)
+        );
+    }
 
-        let _inf = parser.add_subcommand(sub_sub_sub, "-i", 0, "Infinite nesting!");
+    #[test]
+    fn mixed_string_types() {
+        let mut parser = Parser::new();
+        let x = parser.add("--x", 0, String::from("Help."));
+        parser
+            .add_subcommand(x, String::from("--y"), 0, "Help.")
+            .unwrap();
+        parser
+            .command("cmd", String::from("Help."))
+            .add("-z", 0, String::new());
+        parser.add_example("myprog --x", String::from("Do x."));
 
-        parser.parse(Some(vec!["--help"])).unwrap();
+        assert!(parser.parse(Some(vec!["--x", "--y"])).is_ok());
     }
 
     #[test]
-    fn parsed_args() {
+    fn add_all() {
         let mut parser = Parser::new();
-        parser.add("--hello", 0, "hello");
-        let arg = parser.add("arg", 3, "hello");
-        parser.add_template(
-            Template::new()
-                .matches("-not-found")
-                .number_of_values(0)
-                .with_help("bad.")
-                .on_parse(|_| ()),
+        let ids = parser.add_all(&[("-v", 0, "Verbose."), ("--say", 1, "Say.")]);
+        let subs = parser
+            .add_subcommands_all(ids[1], &[("-a", 0, "A."), ("-b", 0, "B.")])
+            .unwrap();
+        assert_eq!(subs.len(), 2);
+        assert_eq!(
+            parser.add_subcommands_all(42, &[("-c", 0, "C.")]),
+            Err(HpError::UnknownParent(42))
         );
 
-        let result = parser.parse(Some(vec!["--hello", "arg", "h", "w", "x"]));
+        let result = parser.parse(Some(vec!["--say", "x", "-b", "-v"])).unwrap();
+        assert!(result.has_with_id(ids[0]) && result.has_with_id(subs[1]));
+    }
 
-        assert!(result.is_ok());
-        let r = result.expect("bad");
+    #[test]
+    fn subcommand_to_many() {
+        let mut parser = Parser::new();
+        let migrate = parser.add("migrate", 0, "Run the migrations.");
+        let dump = parser.add("dump", 0, "Dump the database.");
+        let url = Template::new().matches("--db-url").number_of_values(1);
+        assert_eq!(
+            parser.add_subcommand_to_many(&[migrate, 42], url.clone()),
+            Err(HpError::UnknownParent(42))
+        );
+        let ids = parser
+            .add_subcommand_to_many(&[migrate, dump], url)
+            .unwrap();
 
-        assert!(r.has("--hello"));
-        assert!(r.get_with_id(arg).unwrap().values.len() == 3);
-        assert!(!r.has("-not-found"))
+        let result = parser
+            .parse(Some(vec!["dump", "--db-url", "postgres://localhost"]))
+            .unwrap();
+        let value = result.get_with_context(dump, "--db-url").unwrap();
+        assert_eq!(value.values(), &vec!["postgres://localhost"]);
+        assert!(result.has_with_id(ids[1]) && !result.has_with_id(ids[0]));
+        assert!(parser.parse(Some(vec!["--db-url", "x"])).is_err());
     }
 
     #[test]
-    fn context_parsing() {
+    fn contextual_help() {
         let mut parser = Parser::new()
-            .with_usage("")
-            .with_author("me")
-            .with_description("Example program")
-            .exit_on_help(true);
+            .exit_on_help(false)
+            .contextual_help(true)
+            .with_program_name("myprog")
+            .with_description("My program.");
+        parser.add("--say", 1, "Say something.");
+        let remote = parser.add("remote", 0, "Manage the remotes.");
+        let add = parser
+            .add_subcommand(remote, "add", 2, "Add a remote.")
+            .unwrap();
+        parser
+            .add_subcommand(add, "--fetch", 0, "Fetch it.")
+            .unwrap();
 
-        parser.add("--say", 0, "Repeat something");
-        let expand = parser.add_template(
-            Template::new()
-                .matches("-x")
-                .matches("--expand")
-                .optional_values(false)
-                .number_of_values(0)
-                .with_help("Expand something."),
+        let help = |args: Vec<&str>| match parser.parse(Some(args)) {
+            Err(HpError::HelpRequested(help)) => help,
+            other => panic!("{other:?}"),
+        };
+        let scoped = help(vec!["remote", "--help"]);
+        assert!(scoped.starts_with("myprog remote: Manage the remotes.\nArguments:\n"));
+        assert!(scoped.contains("add [2 value/s]") && scoped.contains("--fetch"));
+        assert!(!scoped.contains("--say"));
+        assert_eq!(
+            help(vec!["remote", "add", "a", "b", "-h"]).lines().next(),
+            Some("myprog remote add: Add a remote.")
+        );
+        assert_eq!(
+            help(vec!["remote", "add", "a", "b", "--fetch", "-h"]),
+            help(vec!["remote", "add", "a", "b", "-h"])
         );
+        assert_eq!(help(vec!["--say", "x", "-h"]), parser.help_string());
+    }
 
-        let sub_sub = parser.add_subcommand_template(
-            expand,
+    #[test]
+    fn value_delimiter() {
+        let mut parser = Parser::new();
+        let tags = parser.add_template(
             Template::new()
-                .matches("--string")
-                .number_of_values(0)
-                .with_help("Expands a string"),
+                .matches("--tags")
+                .variadic(true)
+                .value_delimiter(','),
+        );
+        let pair = parser.add_template(
+            Template::new()
+                .matches("--pair")
+                .number_of_values(2)
+                .value_delimiter(','),
         );
-
-        let sub_sub_sub = parser.add_subcommand(sub_sub, "--super-test", 0, "Amazing super test.");
-
-        parser.add_subcommand(sub_sub_sub, "-i", 0, "Infinite nesting!");
 
         let result = parser
-            .parse(Some(vec!["-x", "--string", "--super-test", "-i"]))
+            .parse(Some(vec!["--tags=a,b", "c\\,d", "--pair", "x,y"]))
             .unwrap();
-
-        assert!(result.has_with_context(expand, "--string"));
-        assert!(result.has_with_context(sub_sub, "--super-test"));
-        assert!(result.has_with_context(sub_sub_sub, "-i"));
+        assert_eq!(
+            result.get_with_id(tags).unwrap().values(),
+            &vec!["a", "b", "c,d"]
+        );
+        assert_eq!(result.get_with_id(pair).unwrap().values(), &vec!["x", "y"]);
+        assert_eq!(
+            parser.parse(Some(vec!["--pair", "x,y,z"])).unwrap_err(),
+            HpError::NumberOfValues("--pair".into(), 3, 2, 0)
+        );
     }
 
     #[test]
-    fn out_of_context() {
-        let mut parser: Parser = Parser::new()
-            .with_usage("")
-            .with_author("me")
-            .with_description("Example program")
-            .exit_on_help(true);
-
-        parser.add("--say", 0, "Repeat something");
-        let expand = parser.add_template(
+    fn exact_values() {
+        let mut parser = Parser::new();
+        parser.add_template(
             Template::new()
-                .matches("-x")
-                .matches("--expand")
-                .optional_values(false)
-                .number_of_values(0)
-                .with_help("Expand something."),
+                .matches("--size")
+                .number_of_values(2)
+                .exact_values(true),
         );
+        parser.add("-v", 0, "Verbose.");
 
-        parser.add_subcommand_template(
-            expand,
-            Template::new()
-                .matches("--string")
-                .number_of_values(0)
-                .with_help("Expands a string"),
+        assert!(parser.parse(Some(vec!["--size", "1", "2", "-v"])).is_ok());
+        assert_eq!(
+            parser
+                .parse(Some(vec!["-v", "--size", "1", "2", "3", "4"]))
+                .unwrap_err(),
+            HpError::TooManyValues("--size".into(), 4, 2, 1)
         );
+    }
 
-        let result = parser.parse(Some(vec!["--string"]));
+    #[test]
+    fn extract() {
+        #[derive(Debug, PartialEq)]
+        struct Opts {
+            verbose: bool,
+            output: String,
+            jobs: u32,
+        }
 
-        assert!(result.is_err());
+        impl FromParsed for Opts {
+            fn from_parsed(result: &ParsedArguments) -> Result<Self, HpError> {
+                Ok(Self {
+                    verbose: result.has("--verbose"),
+                    output: result.require_as("--output")?,
+                    jobs: result.get_as("--jobs")?.unwrap_or(1),
+                })
+            }
+        }
 
-        println!("{}", result.err().unwrap())
+        let mut parser = Parser::new();
+        parser.add_all(&[
+            ("--verbose", 0, "Verbose."),
+            ("--output", 1, "Output."),
+            ("--jobs", 1, "Jobs."),
+        ]);
+
+        let opts: Opts = parser
+            .parse(Some(vec!["--output", "out", "--verbose"]))
+            .unwrap()
+            .extract()
+            .unwrap();
+        let expected = Opts {
+            verbose: true,
+            output: "out".into(),
+            jobs: 1,
+        };
+        assert_eq!(opts, expected);
+
+        let result = parser
+            .parse(Some(vec!["--output", "o", "--jobs", "x"]))
+            .unwrap();
+        assert!(matches!(
+            result.extract::<Opts>(),
+            Err(HpError::ParseFailed(..))
+        ));
+        let result = parser.parse(Some(vec![])).unwrap();
+        assert_eq!(
+            result.extract::<Opts>(),
+            Err(HpError::MissingRequired(vec!["--output".into()]))
+        );
     }
 
     #[test]
-    fn action() {
-        let mut parser = Parser::new();
-        let mut last_val = String::new();
+    fn capture_unknown() {
+        let mut parser = Parser::new().capture_unknown(true).strict(true);
+        parser.add("--release", 0, "Release.");
+        parser.add("--say", 1, "Say.");
 
-        parser.add_template(
-            Template::new()
-                .matches("say")
-                .on_parse(move |values| {
-                    println!("Saying: ");
-                    for each in values.iter() {
-                        last_val = each.to_string().clone();
-                    }
+        let result = parser
+            .parse(Some(vec![
+                "-C",
+                "--release",
+                "--emit=asm",
+                "--say",
+                "-x",
+                "file",
+                "-",
+            ]))
+            .unwrap();
+        assert_eq!(result.unknown(), &vec!["-C", "--emit=asm"]);
+        assert_eq!(result.get("--say").unwrap().values(), &vec!["-x"]);
+        assert_eq!(result.positionals(), &vec!["file", "-"]);
+    }
 
-                    println!("Last val {last_val}");
-                })
-                .number_of_values(8)
-                .optional_values(true),
-        );
+    #[test]
+    fn template_id_of() {
+        let mut parser = Parser::new();
+        let expand = parser.add_template(Template::new().matches("-x").matches("--expand"));
+        let say = parser.add("--say", 1, "Say.");
+        parser.add_subcommand(say, "-s", 0, "Sub.").unwrap();
 
-        assert!(parser.parse(Some(vec!["say", "hello", "world"])).is_ok())
+        assert_eq!(parser.template_id_of("--expand"), Some(expand));
+        assert_eq!(parser.template_id_of("-s"), None);
+        assert!(parser.same_template("-x", "--expand"));
+        assert!(!parser.same_template("-x", "--say"));
+        assert!(!parser.same_template("-y", "-y"));
     }
 }
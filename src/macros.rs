@@ -0,0 +1,56 @@
+//! Module containing the `parser!` macro.
+
+/// Build a `Parser` from a compact description of its arguments.
+///
+/// Every argument is written as `"name"(number_of_values) => "help"`, optionally followed by its
+/// subcommands in braces. The `name` and `desc` fields are optional and set the program name and
+/// the description.
+///
+/// ```
+/// let parser = hp::parser! {
+///     name: "myprog",
+///     desc: "My amazing program!",
+///     args: {
+///         "--say"(1) => "Say something.",
+///         "-c"(0) => "Count something." {
+///             "--add"(2) => "Add two numbers.",
+///         },
+///     }
+/// };
+///
+/// let result = parser.parse(Some(vec!["-c", "--add", "1", "2"])).unwrap();
+/// assert!(result.has("-c"));
+/// ```
+#[macro_export]
+macro_rules! parser {
+    ($(name: $name:expr,)? $(desc: $desc:expr,)? args: { $($args:tt)* } $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut parser = $crate::Parser::new();
+        $(parser = parser.with_program_name($name);)?
+        $(parser = parser.with_description($desc);)?
+        $crate::parser!(@args parser, None, $($args)*);
+        parser
+    }};
+
+    (@args $parser:ident, $parent:expr, ) => {};
+    (@args $parser:ident, $parent:expr, $m:literal ($n:expr) => $help:literal { $($sub:tt)* } $(, $($rest:tt)*)?) => {
+        {
+            let id = $crate::parser!(@add $parser, $parent, $m, $n, $help);
+            $crate::parser!(@args $parser, Some(id), $($sub)*);
+        }
+        $crate::parser!(@args $parser, $parent, $($($rest)*)?);
+    };
+    (@args $parser:ident, $parent:expr, $m:literal ($n:expr) => $help:literal $(, $($rest:tt)*)?) => {
+        $crate::parser!(@add $parser, $parent, $m, $n, $help);
+        $crate::parser!(@args $parser, $parent, $($($rest)*)?);
+    };
+
+    (@add $parser:ident, $parent:expr, $m:literal, $n:expr, $help:literal) => {
+        match $parent {
+            Some(parent) => $parser
+                .add_subcommand(parent, $m, $n, $help)
+                .expect("the parent template was added by the macro"),
+            None => $parser.add($m, $n, $help),
+        }
+    };
+}
@@ -0,0 +1,127 @@
+//! Module containing the man page generation.
+use std::fmt::Write;
+
+use crate::{Parser, Template};
+
+/// Escape a string for use in troff text.
+fn escape(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+impl Parser {
+    /// Generate a man page, in the troff format, describing the program and its arguments.
+    ///
+    /// Subcommands are listed indented under their parent command.
+    ///
+    /// ```ignore
+    /// let parser = Parser::new()
+    ///                 .with_program_name("myprog")
+    ///                 .with_description("My amazing program!");
+    ///
+    /// std::fs::write("myprog.1", parser.generate_manpage())?;
+    /// ```
+    pub fn generate_manpage(&self) -> String {
+        let templates = self.unique_templates();
+        let name = escape(&self.program_name);
+        let mut result = String::new();
+
+        writeln!(result, ".TH {} 1", name.to_uppercase()).unwrap_or(());
+        writeln!(result, ".SH NAME").unwrap_or(());
+        if self.description.is_empty() {
+            writeln!(result, "{name}").unwrap_or(());
+        } else {
+            writeln!(result, "{name} \\- {}", escape(&self.description)).unwrap_or(());
+        }
+
+        writeln!(result, ".SH SYNOPSIS").unwrap_or(());
+        if self.usage.is_empty() {
+            writeln!(result, ".B {name}").unwrap_or(());
+            writeln!(result, "[\\fIarguments\\fR...]").unwrap_or(());
+        } else {
+            writeln!(result, "{}", escape(&self.expand_usage())).unwrap_or(());
+        }
+
+        writeln!(result, ".SH OPTIONS").unwrap_or(());
+        write_options(&mut result, &templates, None);
+        if self.auto_help && !self.help_flags.is_empty() {
+            let flags: Vec<String> = self.help_flags.iter().map(|f| escape(f)).collect();
+            writeln!(
+                result,
+                ".TP\n\\fB{}\\fR\nPrint the help message.",
+                flags.join(", ")
+            )
+            .unwrap_or(());
+        }
+        if self.version.is_some() && !self.version_flags.is_empty() {
+            let flags: Vec<String> = self.version_flags.iter().map(|f| escape(f)).collect();
+            writeln!(
+                result,
+                ".TP\n\\fB{}\\fR\nPrint version information.",
+                flags.join(", ")
+            )
+            .unwrap_or(());
+        }
+
+        if !self.author.is_empty() {
+            writeln!(result, ".SH AUTHOR").unwrap_or(());
+            writeln!(result, "{}", escape(&self.author)).unwrap_or(());
+        }
+
+        result
+    }
+}
+
+/// Write a `.TP` entry for every template with the given parent, followed by its subcommands.
+fn write_options(result: &mut String, templates: &[&Template], parent: Option<usize>) {
+    for template in templates
+        .iter()
+        .filter(|t| t.subargument_of == parent && !t.hidden)
+    {
+        let matches: Vec<String> = template.matches.iter().map(|m| escape(m)).collect();
+        writeln!(result, ".TP").unwrap_or(());
+        write!(result, "\\fB{}\\fR", matches.join(", ")).unwrap_or(());
+        if let Some(values) = template.values_label() {
+            write!(result, " \\fI{values}\\fR").unwrap_or(());
+        }
+        writeln!(result).unwrap_or(());
+        writeln!(result, "{}", escape(&template.help)).unwrap_or(());
+
+        if templates
+            .iter()
+            .any(|t| t.subargument_of == Some(template.id))
+        {
+            writeln!(result, ".RS").unwrap_or(());
+            write_options(result, templates, Some(template.id));
+            writeln!(result, ".RE").unwrap_or(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    #[test]
+    fn manpage() {
+        let mut parser = Parser::new()
+            .with_program_name("myprog")
+            .with_description("My amazing program!")
+            .with_author("Me");
+        parser.add("--say", 1, "Say something.");
+        let count = parser.add("-c", 0, "Count something.");
+        parser
+            .add_subcommand(count, "--add", 2, "Add two numbers.")
+            .unwrap();
+
+        let page = parser.generate_manpage();
+        assert!(page.starts_with(".TH MYPROG 1\n.SH NAME\nmyprog \\- My amazing program!\n"));
+        assert!(page.contains(".TP\n\\fB\\-\\-say\\fR \\fI[1 value/s]\\fR\nSay something.\n"));
+        assert!(page.contains(".RS\n.TP\n\\fB\\-\\-add\\fR"));
+        assert!(page.contains(".SH AUTHOR\nMe\n"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Module containing a shell-like splitting of a command line into separate arguments.
+use crate::HpError;
+
+/// Split `line` into arguments at whitespace, like a shell would.
+///
+/// Single quotes keep everything up to the closing quote as is. Double quotes do as well, except
+/// for a backslash followed by `"` or `\`, which escapes it. Outside of quotes, a backslash
+/// escapes any character.
+pub(crate) fn split(line: &str) -> Result<Vec<String>, HpError> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_argument {
+                    result.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            }
+            '\'' => {
+                in_argument = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(HpError::UnterminatedQuote(line.to_string())),
+                    }
+                }
+            }
+            '"' => {
+                in_argument = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                            current.extend(chars.next());
+                        }
+                        Some(c) => current.push(c),
+                        None => return Err(HpError::UnterminatedQuote(line.to_string())),
+                    }
+                }
+            }
+            '\\' => {
+                in_argument = true;
+                current.push(chars.next().unwrap_or('\\'));
+            }
+            c => {
+                in_argument = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_argument {
+        result.push(current);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            split(r#"--say "hello world" 'it''s' a\ b "say \"hi\"" '' \"#).unwrap(),
+            vec!["--say", "hello world", "its", "a b", "say \"hi\"", "", "\\"]
+        );
+        assert_eq!(
+            split("--say 'hello").unwrap_err(),
+            HpError::UnterminatedQuote("--say 'hello".into())
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Module containing helpers for suggesting known arguments in place of mistyped ones.
+
+/// Compute the Levenshtein edit distance between two strings.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Find the candidate closest to `target`, which is at most `max_distance` edits away.
+///
+/// When multiple candidates have the same distance, the first one is returned.
+pub(crate) fn closest<'a, I>(target: &str, candidates: I, max_distance: usize) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut best: Option<(&str, usize)> = None;
+    for candidate in candidates {
+        let distance = levenshtein(target, candidate);
+        if distance <= max_distance && best.is_none_or(|(_, d)| distance < d) {
+            best = Some((candidate, distance));
+        }
+    }
+
+    best.map(|(candidate, _)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("--vebose", "--verbose"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+    }
+
+    #[test]
+    fn closest_candidate() {
+        let candidates = ["--verbose", "--version", "--say"];
+        assert_eq!(closest("--vebose", candidates, 2), Some("--verbose"));
+        assert_eq!(closest("--versio", candidates, 2), Some("--version"));
+        assert_eq!(closest("--completely-different", candidates, 2), None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Module containing the interaction with the terminal.
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Return the width of the terminal, from the `COLUMNS` environment variable or, on Unix, by
+/// asking the terminal on stdout. Returns `None`, if stdout is not a terminal.
+pub(crate) fn term_width() -> Option<usize> {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .or_else(ioctl_width)
+}
+
+/// Print `prompt` to stderr and read a line from stdin, without the line ending. Returns `None`,
+/// if stdin is not a terminal or it's closed.
+pub(crate) fn prompt(prompt: &str) -> Option<String> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return None;
+    }
+    eprint!("{prompt}");
+    io::stderr().flush().ok()?;
+
+    let mut line = String::new();
+    match stdin.lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+fn ioctl_width() -> Option<usize> {
+    use std::ffi::{c_int, c_ulong, c_ushort};
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        ws_row: c_ushort,
+        ws_col: c_ushort,
+        ws_xpixel: c_ushort,
+        ws_ypixel: c_ushort,
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+    const STDOUT: c_int = 1;
+
+    extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    let mut size = WinSize::default();
+    // SAFETY: `isatty()` only inspects the descriptor and `ioctl()` with `TIOCGWINSZ` writes
+    // a `winsize` structure, which `WinSize` mirrors, into the pointed to memory.
+    let ok =
+        unsafe { isatty(STDOUT) == 1 && ioctl(STDOUT, TIOCGWINSZ, &mut size as *mut WinSize) == 0 };
+
+    match ok && size.ws_col > 0 {
+        true => Some(size.ws_col as usize),
+        false => None,
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn ioctl_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width() {
+        assert!(term_width().is_none_or(|width| width > 0));
+        assert!(ioctl_width().is_none_or(|width| width > 0));
+    }
+}
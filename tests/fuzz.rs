@@ -0,0 +1,191 @@
+//! Feed random parser configurations and random command lines to the parser, none of which may
+//! cause a panic.
+use hp::{Parser, Shell, Template};
+
+/// A small xorshift generator, so that the test needs no dependencies and is reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+const NAMES: &[&str] = &[
+    "-a",
+    "-b",
+    "-c",
+    "-x",
+    "--long",
+    "--say",
+    "--color",
+    "--add",
+    "cmd",
+    "sub",
+    "-",
+    "--",
+    "",
+    "a b",
+    "--ü",
+    "-1",
+    "verbose",
+    "--no-color",
+    "@args",
+];
+
+const TOKENS: &[&str] = &[
+    "-a",
+    "-b",
+    "-abc",
+    "-ax",
+    "--long",
+    "--long=v",
+    "--say=",
+    "--color",
+    "--no-color",
+    "--add",
+    "--ad",
+    "cmd",
+    "sub",
+    "-",
+    "--",
+    "=",
+    "-=",
+    "value",
+    "-5",
+    "-0.5",
+    "1",
+    "k=v",
+    "ü",
+    "--ü",
+    "-ü",
+    "",
+    " ",
+    "-h",
+    "--help",
+    "-V",
+    "@missing-file",
+    "'",
+    "\"",
+    "\\",
+];
+
+fn random_template(rng: &mut Rng) -> Template {
+    let mut template = Template::new().with_help("Some help.");
+    for _ in 0..rng.below(3) {
+        template = template.matches(rng.pick(NAMES));
+    }
+    template = template.number_of_values(rng.below(3));
+    let options: [fn(Template, &mut Rng) -> Template; 15] = [
+        |t, _| t.optional_values(true),
+        |t, _| t.variadic(true),
+        |t, r| t.values_range(r.below(3), r.below(4)),
+        |t, r| t.at_least(r.below(3)),
+        |t, _| t.required(true),
+        |t, _| t.global(true),
+        |t, _| t.greedy(true),
+        |t, _| t.key_value(true),
+        |t, _| t.rest(true),
+        |t, _| t.negatable(true),
+        |t, _| t.hidden(true),
+        |t, _| t.accept_dash_values(true),
+        |t, _| t.allow_negative_numbers(true),
+        |t, _| t.default_value("default"),
+        |t, _| t.possible_values(vec!["value".into(), "1".into()]),
+    ];
+    for option in options {
+        if rng.chance(15) {
+            template = option(template, rng);
+        }
+    }
+    if rng.chance(20) {
+        template = template.on_parse_try(|values| match values.len() {
+            2 => Err("two values".into()),
+            _ => Ok(()),
+        });
+    }
+
+    template
+}
+
+fn random_parser(rng: &mut Rng) -> Parser {
+    let mut parser = Parser::new()
+        .exit_on_help(false)
+        .exit_on_version(false)
+        .with_version("1.0")
+        .strict(rng.chance(50))
+        .allow_abbreviations(rng.chance(30))
+        .response_files(rng.chance(20))
+        .normalize_dashes(rng.chance(20))
+        .help_width(rng.below(120));
+
+    let mut ids = Vec::new();
+    for _ in 0..rng.below(8) {
+        let template = random_template(rng);
+        let id = match ids.is_empty() || rng.chance(50) {
+            true => parser.add_template(template),
+            false => {
+                let parent = ids[rng.below(ids.len())];
+                parser
+                    .add_subcommand_template(parent, template)
+                    .unwrap_or(parent)
+            }
+        };
+        ids.push(id);
+    }
+    if rng.chance(20) {
+        parser.command(rng.pick(NAMES), "A command.").add(
+            rng.pick(NAMES),
+            rng.below(2),
+            "An option.",
+        );
+    }
+    if ids.len() > 1 && rng.chance(20) {
+        parser.exclusive_group(vec![ids[0], ids[1]]);
+    }
+    if !ids.is_empty() && rng.chance(20) {
+        parser.remove_with_id(ids[rng.below(ids.len())]);
+    }
+
+    parser
+}
+
+#[test]
+fn random_input() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..3000 {
+        let parser = random_parser(&mut rng);
+        let args: Vec<String> = (0..rng.below(8))
+            .map(|_| rng.pick(TOKENS).to_string())
+            .collect();
+
+        if let Err(err) = parser.parse_from(args.clone()) {
+            let _ = err.pretty(&args);
+            let _ = err.to_string_plain();
+        }
+        let _ = parser.dispatch(Some(args.iter().map(|a| a.as_str()).collect()));
+        let _ = parser.parse_str(&args.join(" "));
+        let _ = parser.validate();
+        let _ = parser.help_string();
+        let _ = parser.synopsis();
+        let _ = parser.generate_manpage();
+        let _ = parser.generate_completion(Shell::Bash);
+        let _ = parser.generate_completion(Shell::Zsh);
+    }
+}